@@ -0,0 +1,185 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A team roster mapping short handles (e.g. initials) to `Name <email>` identities.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Roster {
+    #[serde(default)]
+    pub authors: BTreeMap<String, String>,
+}
+
+/// The path of the user roster, `~/.git-bottle/authors.yml`.
+pub fn user_roster_path() -> Option<PathBuf> {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".git-bottle").join("authors.yml"))
+}
+
+pub fn load(path: &Path) -> Result<Roster, anyhow::Error> {
+    if !path.exists() {
+        return Ok(Roster::default());
+    }
+    let file = fs::File::open(path)
+        .map_err(|e| anyhow!("Could not open file {}: {}", path.display(), e))?;
+    serde_yaml::from_reader(file)
+        .map_err(|e| anyhow!("Malformed authors file {}: {}", path.display(), e))
+}
+
+pub fn save(path: &Path, roster: &Roster) -> Result<(), anyhow::Error> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| anyhow!("Could not create directory {}: {}", dir.display(), e))?;
+    }
+    let content = serde_yaml::to_string(roster).map_err(|e| anyhow!(e))?;
+    fs::write(path, content).map_err(|e| anyhow!("Could not write file {}: {}", path.display(), e))
+}
+
+/// The identity of a handle, case-insensitively.
+pub fn expand<'a>(authors: &'a BTreeMap<String, String>, handle: &str) -> Option<&'a String> {
+    authors
+        .iter()
+        .find(|(h, _)| h.eq_ignore_ascii_case(handle.trim()))
+        .map(|(_, identity)| identity)
+}
+
+/// The options shown for a roster, as `(label, identity)`.
+pub fn choices(authors: &BTreeMap<String, String>) -> Vec<(String, String)> {
+    authors
+        .iter()
+        .map(|(handle, identity)| (format!("{}: {}", handle, identity), identity.clone()))
+        .collect()
+}
+
+/// Reads the authors of a git-mob `.git-coauthors` file.
+pub fn from_git_mob(content: &str) -> Result<BTreeMap<String, String>, anyhow::Error> {
+    #[derive(Deserialize)]
+    struct GitMob {
+        coauthors: BTreeMap<String, GitMobAuthor>,
+    }
+    #[derive(Deserialize)]
+    struct GitMobAuthor {
+        name: String,
+        email: String,
+    }
+
+    let git_mob: GitMob = serde_json::from_str(content).map_err(|e| anyhow!(e))?;
+    Ok(git_mob
+        .coauthors
+        .into_iter()
+        .map(|(handle, a)| (handle, format!("{} <{}>", a.name, a.email)))
+        .collect())
+}
+
+/// Reads the authors of a git-duet `.git-authors` file, where an author is `Name[; username]`.
+pub fn from_git_duet(content: &str) -> Result<BTreeMap<String, String>, anyhow::Error> {
+    #[derive(Deserialize)]
+    struct GitDuet {
+        #[serde(alias = "pairs")]
+        authors: BTreeMap<String, String>,
+        #[serde(default)]
+        email: GitDuetEmail,
+        #[serde(default)]
+        email_addresses: BTreeMap<String, String>,
+    }
+    #[derive(Default, Deserialize)]
+    struct GitDuetEmail {
+        domain: Option<String>,
+    }
+
+    let git_duet: GitDuet = serde_yaml::from_str(content).map_err(|e| anyhow!(e))?;
+    let mut authors = BTreeMap::new();
+    for (handle, author) in git_duet.authors {
+        let (name, username) = match author.split_once(';') {
+            Some((name, username)) => (name.trim(), Some(username.trim().to_string())),
+            None => (author.trim(), None),
+        };
+        let email = match git_duet.email_addresses.get(&handle) {
+            Some(email) => email.clone(),
+            None => {
+                let domain = git_duet.email.domain.as_ref().ok_or_else(|| {
+                    anyhow!("No email address nor email domain for author {}", handle)
+                })?;
+                let username = username.unwrap_or_else(|| default_username(name));
+                format!("{}@{}", username, domain)
+            }
+        };
+        authors.insert(handle, format!("{} <{}>", name, email));
+    }
+    Ok(authors)
+}
+
+/// git-duet's default username: the first initial and the last name, e.g. `f.bar`.
+fn default_username(name: &str) -> String {
+    let words: Vec<&str> = name.split_whitespace().collect();
+    match (words.first(), words.last()) {
+        (Some(first), Some(last)) if words.len() > 1 => {
+            format!("{}.{}", first.chars().next().unwrap_or_default(), last).to_lowercase()
+        }
+        _ => name.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::authors::{expand, from_git_duet, from_git_mob};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_expand() {
+        let authors = BTreeMap::from([(
+            "jd".to_string(),
+            "Jane Doe <jane.doe@example.org>".to_string(),
+        )]);
+
+        assert_eq!(
+            expand(&authors, "JD"),
+            Some(&"Jane Doe <jane.doe@example.org>".to_string())
+        );
+        assert_eq!(expand(&authors, "js"), None);
+    }
+
+    #[test]
+    fn test_from_git_mob() {
+        let content =
+            r#"{"coauthors": {"jd": {"name": "Jane Doe", "email": "jane.doe@example.org"}}}"#;
+
+        assert_eq!(
+            from_git_mob(content).unwrap(),
+            BTreeMap::from([(
+                "jd".to_string(),
+                "Jane Doe <jane.doe@example.org>".to_string()
+            )])
+        );
+    }
+
+    #[test]
+    fn test_from_git_duet() {
+        let content = "authors:
+  jd: Jane Doe; jane
+  js: James Smith
+  fb: Frances Bar
+email:
+  domain: example.org
+email_addresses:
+  fb: frances@example.com
+";
+
+        assert_eq!(
+            from_git_duet(content).unwrap(),
+            BTreeMap::from([
+                (
+                    "fb".to_string(),
+                    "Frances Bar <frances@example.com>".to_string()
+                ),
+                ("jd".to_string(), "Jane Doe <jane@example.org>".to_string()),
+                (
+                    "js".to_string(),
+                    "James Smith <j.smith@example.org>".to_string()
+                ),
+            ])
+        );
+    }
+}
@@ -0,0 +1,449 @@
+use crate::git::Git;
+use crate::structure::{CommitStructure, Trailer, Validate, Values};
+use crate::when;
+use anyhow::anyhow;
+use regex::Regex;
+use thiserror::Error;
+
+pub const CO_AUTHORED_BY: &str = "Co-authored-by";
+
+#[derive(Debug, PartialEq, Error)]
+pub enum Violation {
+    #[error("missing required trailer \"{name}\"")]
+    Missing { name: String },
+    #[error("\"{name}: {value}\" is not one of the allowed values: {}", options.join(", "))]
+    NotAllowed {
+        name: String,
+        value: String,
+        options: Vec<String>,
+    },
+    #[error("\"{name}\" must appear at most once, found {count}")]
+    Repeated { name: String, count: usize },
+    #[error("\"{name}: {value}\" does not match the pattern {pattern}")]
+    NoMatch {
+        name: String,
+        value: String,
+        pattern: String,
+    },
+    #[error("\"{name}: {value}\" is not in the format \"Name <email>\"")]
+    InvalidIdentity { name: String, value: String },
+    #[error("\"{name}: {value}\" is {length} characters long, at most {max} are allowed")]
+    TooLong {
+        name: String,
+        value: String,
+        length: usize,
+        max: usize,
+    },
+    #[error("\"{name}\" must appear at most {max} times, found {count}")]
+    TooMany {
+        name: String,
+        count: usize,
+        max: usize,
+    },
+}
+
+/// Checks the trailers of a commit message against the ones declared in the configuration. A
+/// trailer whose conditions do not hold, or cannot be evaluated, is optional.
+pub fn check<G: Git>(
+    commit_structure: &CommitStructure,
+    trailers: &[(String, String)],
+    context: &mut when::Context<G>,
+) -> Result<Vec<Violation>, anyhow::Error> {
+    let mut violations = vec![];
+    for trailer in &commit_structure.trailers {
+        let values: Vec<String> = trailers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(trailer.name()))
+            .map(|(_, value)| value.clone())
+            .collect();
+        let applies = match trailer.when() {
+            Some(when) => context.holds(when, trailers).unwrap_or(false),
+            None => true,
+        };
+        violations.extend(
+            check_trailer(trailer, &values)?
+                .into_iter()
+                .filter(|v| applies || !matches!(v, Violation::Missing { .. })),
+        );
+    }
+
+    let configured = |key: &str| {
+        commit_structure
+            .trailers
+            .iter()
+            .any(|t| t.name().eq_ignore_ascii_case(key))
+    };
+    for (key, value) in trailers {
+        if key.eq_ignore_ascii_case(CO_AUTHORED_BY) && !configured(key) && !is_identity(value) {
+            violations.push(Violation::InvalidIdentity {
+                name: key.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+    Ok(violations)
+}
+
+/// Checks the values of a trailer against its definition and its rules.
+pub fn check_trailer(
+    trailer: &Trailer,
+    values: &[String],
+) -> Result<Vec<Violation>, anyhow::Error> {
+    let name = trailer.name();
+    let mut violations = check_count(name, trailer.validate(), values.len());
+    if values.is_empty() {
+        return Ok(violations);
+    }
+
+    let (single_valued, options) = match trailer {
+        Trailer::Text(_) => (true, None),
+        Trailer::Select(t) => (true, allowed_options(&t.values)),
+        Trailer::MultiSelect(t) => (false, allowed_options(&t.values)),
+    };
+    if single_valued && values.len() > 1 {
+        violations.push(Violation::Repeated {
+            name: name.to_string(),
+            count: values.len(),
+        });
+    }
+    if let Some(options) = options {
+        for value in values.iter().filter(|v| !options.contains(v)) {
+            violations.push(Violation::NotAllowed {
+                name: name.to_string(),
+                value: value.to_string(),
+                options: options.clone(),
+            });
+        }
+    }
+    for value in values {
+        violations.extend(check_value(name, trailer.validate(), value)?);
+    }
+    Ok(violations)
+}
+
+/// Checks the number of values of a trailer against its rules.
+pub fn check_count(name: &str, validate: &Validate, count: usize) -> Vec<Violation> {
+    let mut violations = vec![];
+    if count == 0 && validate.required {
+        violations.push(Violation::Missing {
+            name: name.to_string(),
+        });
+    }
+    if let Some(max) = validate.max_count.filter(|max| count > *max) {
+        violations.push(Violation::TooMany {
+            name: name.to_string(),
+            count,
+            max,
+        });
+    }
+    violations
+}
+
+/// Checks a value of a trailer against its rules. `Co-authored-by` values are always identities.
+pub fn check_value(
+    name: &str,
+    validate: &Validate,
+    value: &str,
+) -> Result<Vec<Violation>, anyhow::Error> {
+    let mut violations = vec![];
+    if let Some(pattern) = &validate.pattern {
+        let regex = Regex::new(pattern)
+            .map_err(|e| anyhow!("Invalid pattern for trailer {}: {}", name, e))?;
+        if !regex.is_match(value) {
+            violations.push(Violation::NoMatch {
+                name: name.to_string(),
+                value: value.to_string(),
+                pattern: pattern.clone(),
+            });
+        }
+    }
+    let length = value.chars().count();
+    if let Some(max) = validate.max_length.filter(|max| length > *max) {
+        violations.push(Violation::TooLong {
+            name: name.to_string(),
+            value: value.to_string(),
+            length,
+            max,
+        });
+    }
+    if is_identity_trailer(name, validate) && !is_identity(value) {
+        violations.push(Violation::InvalidIdentity {
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+    }
+    Ok(violations)
+}
+
+/// Whether the values of the trailer must be identities.
+pub fn is_identity_trailer(name: &str, validate: &Validate) -> bool {
+    validate.identity || name.eq_ignore_ascii_case(CO_AUTHORED_BY)
+}
+
+/// Whether the value is in the `Name <email>` format.
+pub fn is_identity(value: &str) -> bool {
+    let regex = Regex::new(r"^[^<>]+ <[^<>@\s]+@[^<>\s]+>$").unwrap();
+    regex.is_match(value)
+}
+
+fn allowed_options(values: &Values) -> Option<Vec<String>> {
+    match values {
+        Values::FromOptions(v) => Some(v.options.clone()),
+        Values::FromGitLog(_) | Values::FromAuthors(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::check::{check, check_count, check_value, Violation};
+    use crate::git::MockGit;
+    use crate::structure::{
+        CommitStructure, MultiSelectTrailer, SelectTrailer, TextTrailer, Trailer, Validate, Values,
+        ValuesFromOptions, When,
+    };
+    use crate::when::Context;
+
+    fn commit_structure() -> CommitStructure {
+        CommitStructure {
+            trailers: vec![
+                Trailer::Text(TextTrailer {
+                    name: "Issue".to_string(),
+                    when: None,
+                    validate: Validate {
+                        required: true,
+                        pattern: Some(r"^#\d+$".to_string()),
+                        ..Default::default()
+                    },
+                    default: None,
+                }),
+                Trailer::Select(SelectTrailer {
+                    name: "Type".to_string(),
+                    when: None,
+                    validate: Validate::default(),
+                    default: None,
+                    values: Values::FromOptions(ValuesFromOptions {
+                        options: vec!["feature".to_string(), "fix".to_string()],
+                    }),
+                }),
+                Trailer::MultiSelect(MultiSelectTrailer {
+                    name: "Co-authored-by".to_string(),
+                    when: None,
+                    validate: Validate::default(),
+                    default: None,
+                    values: Values::FromOptions(ValuesFromOptions {
+                        options: vec!["Jane Doe <jane.doe@example.org>".to_string()],
+                    }),
+                }),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn trailer(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn test_valid_trailers() {
+        let trailers = vec![
+            trailer("issue", "#42"),
+            trailer("Type", "fix"),
+            trailer("Co-authored-by", "Jane Doe <jane.doe@example.org>"),
+        ];
+
+        assert_eq!(
+            check(
+                &commit_structure(),
+                &trailers,
+                &mut Context::<MockGit>::answers_only()
+            )
+            .unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_missing_required_trailer() {
+        assert_eq!(
+            check(
+                &commit_structure(),
+                &[trailer("Type", "fix")],
+                &mut Context::<MockGit>::answers_only()
+            )
+            .unwrap(),
+            vec![Violation::Missing {
+                name: "Issue".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_required_trailer_with_conditions() {
+        let commit_structure = CommitStructure {
+            trailers: vec![Trailer::Text(TextTrailer {
+                name: "Reviewed-by".to_string(),
+                when: Some(When {
+                    branch: vec!["main".to_string()],
+                    ..Default::default()
+                }),
+                validate: Validate {
+                    required: true,
+                    ..Default::default()
+                },
+                default: None,
+            })],
+            ..Default::default()
+        };
+        let on = |branch: &'static str| {
+            let mut git: MockGit = MockGit::new();
+            git.expect_current_branch()
+                .returning(move || Ok(Some(branch.to_string())));
+            git
+        };
+
+        assert_eq!(
+            check(&commit_structure, &[], &mut Context::new(&on("main"))).unwrap(),
+            vec![Violation::Missing {
+                name: "Reviewed-by".to_string()
+            }]
+        );
+        assert_eq!(
+            check(
+                &commit_structure,
+                &[],
+                &mut Context::new(&on("feature/login"))
+            )
+            .unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            check(
+                &commit_structure,
+                &[],
+                &mut Context::<MockGit>::answers_only()
+            )
+            .unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_value_not_in_options() {
+        let trailers = vec![
+            trailer("Issue", "#42"),
+            trailer("Co-authored-by", "Joe Shmoe <joe.shmoe@example.org>"),
+        ];
+
+        assert_eq!(
+            check(
+                &commit_structure(),
+                &trailers,
+                &mut Context::<MockGit>::answers_only()
+            )
+            .unwrap(),
+            vec![Violation::NotAllowed {
+                name: "Co-authored-by".to_string(),
+                value: "Joe Shmoe <joe.shmoe@example.org>".to_string(),
+                options: vec!["Jane Doe <jane.doe@example.org>".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_single_valued_trailer_repeated() {
+        let trailers = vec![
+            trailer("Issue", "#42"),
+            trailer("Type", "fix"),
+            trailer("Type", "fix"),
+        ];
+
+        assert_eq!(
+            check(
+                &commit_structure(),
+                &trailers,
+                &mut Context::<MockGit>::answers_only()
+            )
+            .unwrap(),
+            vec![Violation::Repeated {
+                name: "Type".to_string(),
+                count: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn test_value_not_matching_pattern() {
+        assert_eq!(
+            check(
+                &commit_structure(),
+                &[trailer("Issue", "ABC-42")],
+                &mut Context::<MockGit>::answers_only()
+            )
+            .unwrap(),
+            vec![Violation::NoMatch {
+                name: "Issue".to_string(),
+                value: "ABC-42".to_string(),
+                pattern: r"^#\d+$".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_co_author_not_an_identity() {
+        let trailers = vec![
+            trailer("Issue", "#42"),
+            trailer("Co-authored-by", "Jane Doe <jane.doe@example.org>"),
+            trailer("co-authored-by", "jane.doe@example.org"),
+        ];
+
+        assert_eq!(
+            check(
+                &commit_structure(),
+                &trailers,
+                &mut Context::<MockGit>::answers_only()
+            )
+            .unwrap(),
+            vec![
+                Violation::NotAllowed {
+                    name: "Co-authored-by".to_string(),
+                    value: "jane.doe@example.org".to_string(),
+                    options: vec!["Jane Doe <jane.doe@example.org>".to_string()],
+                },
+                Violation::InvalidIdentity {
+                    name: "Co-authored-by".to_string(),
+                    value: "jane.doe@example.org".to_string(),
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rules() {
+        let validate = Validate {
+            identity: true,
+            max_count: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            check_count("Reviewed-by", &validate, 3),
+            vec![Violation::TooMany {
+                name: "Reviewed-by".to_string(),
+                count: 3,
+                max: 2
+            }]
+        );
+        assert_eq!(check_count("Reviewed-by", &validate, 0), vec![]);
+        assert_eq!(
+            check_value("Reviewed-by", &validate, "jane.doe@example.org").unwrap(),
+            vec![Violation::InvalidIdentity {
+                name: "Reviewed-by".to_string(),
+                value: "jane.doe@example.org".to_string(),
+            }]
+        );
+        assert_eq!(
+            check_value("Reviewed-by", &validate, "Jane Doe <jane.doe@example.org>").unwrap(),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,448 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "git-bottle",
+    bin_name = "git bottle",
+    version,
+    about = "Interactive CLI to simplify the usage of Co-authored-by and other commit trailers",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub commit: CommitArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Prompt for a message and trailers, then run `git commit` (default)
+    Commit(CommitArgs),
+    /// Edit the message and trailers of the last commit, then run `git commit --amend`
+    Amend(CommitArgs),
+    /// Edit the message and trailers of an older commit, then rewrite the history with a rebase
+    Reword {
+        /// The commit to reword
+        rev: String,
+    },
+    /// Prompt for a message and trailers, then print the message without committing
+    Message(MessageArgs),
+    /// Run as a git hook, or install it in the repository
+    Hook(HookArgs),
+    /// Share trailers, e.g. co-authors, across the commits of a pair/mob session
+    Session(SessionArgs),
+    /// Manage the roster of authors mapping handles to identities
+    Authors(AuthorsArgs),
+    /// Create a .git-bottle.yml configuration in the repository, answering a few questions
+    Init,
+    /// Show, locate or validate the configuration
+    Config(ConfigArgs),
+    /// Check the trailers of a commit message file against the configuration (commit-msg hook)
+    Check {
+        /// The file containing the commit message
+        file: PathBuf,
+    },
+    /// Check the trailers of every commit in a revision range against the configuration
+    Lint {
+        /// The revision range, e.g. origin/main..HEAD
+        range: String,
+        /// The output format
+        #[arg(long, value_enum, default_value_t = LintFormat::Human)]
+        format: LintFormat,
+    },
+}
+
+#[derive(Debug, Default, Args)]
+pub struct CommitArgs {
+    /// Replace the tip of the current branch by creating a new commit, editing its message
+    #[arg(long)]
+    pub amend: bool,
+
+    /// Automatically stage files that have been modified and deleted
+    #[arg(short = 'a', long)]
+    pub all: bool,
+
+    /// Bypass the pre-commit and commit-msg hooks
+    #[arg(short = 'n', long)]
+    pub no_verify: bool,
+
+    /// GPG-sign the commit, optionally with the given key id, e.g. -SABCD or --gpg-sign=ABCD
+    #[arg(
+        short = 'S',
+        long = "gpg-sign",
+        value_name = "KEYID",
+        num_args = 0..=1,
+        default_missing_value = ""
+    )]
+    pub gpg_sign: Option<String>,
+
+    /// Allow recording a commit that has the exact same tree as its sole parent
+    #[arg(long)]
+    pub allow_empty: bool,
+
+    /// Print the message instead of committing
+    #[arg(long)]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub given: GivenArgs,
+
+    /// Same as -m
+    #[arg(value_name = "MESSAGE", conflicts_with = "message")]
+    pub message_arg: Option<String>,
+
+    /// Extra arguments passed as they are to `git commit`
+    #[arg(last = true, value_name = "GIT_COMMIT_ARGS")]
+    pub git_args: Vec<String>,
+}
+
+/// Answers given on the command line instead of prompted for.
+#[derive(Debug, Default, Args)]
+pub struct GivenArgs {
+    /// Add co-authors by their comma-separated handles in the authors roster, e.g. jd,ab
+    #[arg(
+        short = 'w',
+        long = "with",
+        value_name = "HANDLES",
+        value_delimiter = ','
+    )]
+    pub with: Vec<String>,
+
+    /// Use this message, skipping its prompt
+    #[arg(short = 'm', long = "message", value_name = "MESSAGE")]
+    pub message: Option<String>,
+
+    /// Add a trailer, skipping its prompt, e.g. Issue=ABC-1 (repeatable)
+    #[arg(long = "trailer", value_name = "KEY=VALUE", value_parser = parse_trailer)]
+    pub trailers: Vec<(String, String)>,
+
+    /// Fail instead of prompting for the answers which are not given
+    #[arg(long)]
+    pub no_interactive: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct MessageArgs {
+    #[command(flatten)]
+    pub given: GivenArgs,
+
+    /// Write the message to a file instead of the standard output
+    #[arg(short = 'o', long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct HookArgs {
+    #[command(subcommand)]
+    pub command: HookCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HookCommand {
+    /// Prompt for trailers and append them to the message file prepared by git
+    PrepareCommitMsg {
+        /// The file containing the commit message
+        file: PathBuf,
+        /// The source of the commit message (message, template, merge, squash or commit)
+        source: Option<String>,
+        /// The commit object name, when the source is commit
+        sha: Option<String>,
+    },
+    /// Install a hook running git-bottle in the repository
+    Install {
+        #[arg(value_enum, default_value_t = Hook::PrepareCommitMsg)]
+        hook: Hook,
+    },
+    /// Remove a hook previously installed by git-bottle
+    Uninstall {
+        #[arg(value_enum, default_value_t = Hook::PrepareCommitMsg)]
+        hook: Hook,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Hook {
+    PrepareCommitMsg,
+    CommitMsg,
+}
+
+impl Hook {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hook::PrepareCommitMsg => "prepare-commit-msg",
+            Hook::CommitMsg => "commit-msg",
+        }
+    }
+
+    /// The git-bottle command run by the hook
+    pub fn command(&self) -> &'static str {
+        match self {
+            Hook::PrepareCommitMsg => "hook prepare-commit-msg",
+            Hook::CommitMsg => "check",
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct SessionArgs {
+    #[command(subcommand)]
+    pub command: SessionCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SessionCommand {
+    /// Prompt for the multi-selection trailers pre-selected in every commit of the session
+    Start {
+        /// How long the session lasts, e.g. 90m, 8h or 2d
+        #[arg(long, value_name = "DURATION", default_value = "8h")]
+        expires_in: String,
+    },
+    /// Show the trailers of the current session
+    Show,
+    /// Add a value to a trailer of the current session
+    Add {
+        /// The value, e.g. Jane Doe <jane.doe@example.org>
+        who: String,
+        /// The name of the trailer
+        #[arg(long, default_value = "Co-authored-by")]
+        trailer: String,
+    },
+    /// Remove a value from a trailer of the current session
+    Remove {
+        /// The value, e.g. Jane Doe <jane.doe@example.org>
+        who: String,
+        /// The name of the trailer
+        #[arg(long, default_value = "Co-authored-by")]
+        trailer: String,
+    },
+    /// End the current session
+    End,
+}
+
+#[derive(Debug, Args)]
+pub struct AuthorsArgs {
+    #[command(subcommand)]
+    pub command: AuthorsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AuthorsCommand {
+    /// Import the authors of git-mob (.git-coauthors) and git-duet (.git-authors) files
+    Import {
+        /// The files to import, by default ~/.git-coauthors and ~/.git-authors
+        files: Vec<PathBuf>,
+        /// The roster to import the authors into
+        #[arg(short = 'o', long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the effective configuration, with the origin of each key
+    Show,
+    /// Print the configuration files in use, from the lowest to the highest precedence
+    Path,
+    /// Validate a configuration file, by default all the configuration files in use
+    Validate {
+        /// The configuration file to validate
+        file: Option<PathBuf>,
+    },
+    /// Print the JSON Schema of the configuration, for editor completion and validation
+    Schema,
+    /// Upgrade a configuration file to the latest version of the format, by default all the
+    /// configuration files in use
+    Migrate {
+        /// The configuration file to upgrade
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LintFormat {
+    Human,
+    Json,
+    Junit,
+}
+
+/// Parses a `--trailer` argument, `Key=value` or `Key: value` as for `git commit --trailer`.
+fn parse_trailer(arg: &str) -> Result<(String, String), String> {
+    let index = arg
+        .find(['=', ':'])
+        .ok_or_else(|| format!("expected KEY=VALUE, found {}", arg))?;
+    let (key, value) = (arg[..index].trim(), arg[index + 1..].trim());
+    if key.is_empty() {
+        return Err(format!("expected KEY=VALUE, found {}", arg));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+impl CommitArgs {
+    /// The message given by -m or as an argument.
+    pub fn given_message(&self) -> Option<&String> {
+        self.given.message.as_ref().or(self.message_arg.as_ref())
+    }
+
+    pub fn to_git_args(&self) -> Vec<String> {
+        let gpg_sign = self.gpg_sign.as_ref().map(|key| match key.as_str() {
+            "" => "--gpg-sign".to_string(),
+            _ => format!("--gpg-sign={}", key),
+        });
+        vec![
+            self.amend.then(|| "--amend".to_string()),
+            self.all.then(|| "--all".to_string()),
+            self.no_verify.then(|| "--no-verify".to_string()),
+            gpg_sign,
+            self.allow_empty.then(|| "--allow-empty".to_string()),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(self.git_args.iter().cloned())
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::{Cli, Command};
+    use clap::Parser;
+    use std::path::PathBuf;
+
+    fn git_args(args: &[&str]) -> Vec<String> {
+        let cli = Cli::parse_from(args);
+        match cli.command {
+            Some(Command::Commit(commit)) => commit.to_git_args(),
+            None => cli.commit.to_git_args(),
+            Some(command) => panic!("unexpected command {:?}", command),
+        }
+    }
+
+    #[test]
+    fn test_no_arguments() {
+        assert!(git_args(&["git-bottle"]).is_empty());
+    }
+
+    #[test]
+    fn test_flags_are_forwarded() {
+        assert_eq!(
+            git_args(&[
+                "git-bottle",
+                "--amend",
+                "-a",
+                "--no-verify",
+                "-S",
+                "--allow-empty"
+            ]),
+            vec![
+                "--amend",
+                "--all",
+                "--no-verify",
+                "--gpg-sign",
+                "--allow-empty"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commit_subcommand_with_extra_arguments() {
+        assert_eq!(
+            git_args(&[
+                "git-bottle",
+                "commit",
+                "--gpg-sign=ABCD",
+                "--",
+                "--author",
+                "Jane Doe <jane.doe@example.org>"
+            ]),
+            vec![
+                "--gpg-sign=ABCD",
+                "--author",
+                "Jane Doe <jane.doe@example.org>"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gpg_sign_with_a_key_id() {
+        assert_eq!(git_args(&["git-bottle", "-SABCD"]), vec!["--gpg-sign=ABCD"]);
+        assert_eq!(
+            git_args(&["git-bottle", "-S", "--allow-empty"]),
+            vec!["--gpg-sign", "--allow-empty"]
+        );
+
+        // a separate value is the key id, never the message
+        let cli = Cli::parse_from(["git-bottle", "-S", "ABCD"]);
+        assert_eq!(cli.commit.gpg_sign, Some("ABCD".to_string()));
+        assert_eq!(cli.commit.given_message(), None);
+    }
+
+    #[test]
+    fn test_dry_run_is_not_forwarded() {
+        assert_eq!(git_args(&["git-bottle", "--dry-run", "-a"]), vec!["--all"]);
+    }
+
+    #[test]
+    fn test_given_answers_are_not_forwarded() {
+        let cli = Cli::parse_from([
+            "git-bottle",
+            "-m",
+            "Fix the build",
+            "--trailer",
+            "Issue=ABC-1",
+            "--trailer",
+            "Co-authored-by: jd",
+            "--no-interactive",
+        ]);
+
+        assert_eq!(
+            cli.commit.given_message(),
+            Some(&"Fix the build".to_string())
+        );
+        assert_eq!(
+            cli.commit.given.trailers,
+            vec![
+                ("Issue".to_string(), "ABC-1".to_string()),
+                ("Co-authored-by".to_string(), "jd".to_string())
+            ]
+        );
+        assert!(cli.commit.given.no_interactive);
+        assert!(cli.commit.to_git_args().is_empty());
+        assert!(Cli::try_parse_from(["git-bottle", "--trailer", "Issue"]).is_err());
+    }
+
+    #[test]
+    fn test_message_subcommand() {
+        let cli = Cli::parse_from([
+            "git-bottle",
+            "message",
+            "-o",
+            "MSG",
+            "-m",
+            "Fix the build",
+            "--trailer",
+            "Issue=ABC-1",
+            "--no-interactive",
+        ]);
+        match cli.command {
+            Some(Command::Message(args)) => {
+                assert_eq!(args.output, Some(PathBuf::from("MSG")));
+                assert_eq!(args.given.message, Some("Fix the build".to_string()));
+                assert_eq!(
+                    args.given.trailers,
+                    vec![("Issue".to_string(), "ABC-1".to_string())]
+                );
+                assert!(args.given.no_interactive);
+            }
+            command => panic!("unexpected command {:?}", command),
+        }
+    }
+}
@@ -1,43 +1,798 @@
-use crate::structure::{CommitStructure, MultiSelectTrailer, Trailer, Values, ValuesFromGitLog};
+use crate::authors;
+use crate::git::Git;
+use crate::message;
+use crate::migrate;
+use crate::structure::{
+    CommitStructure, MultiSelectTrailer, Order, Trailer, TrailersMerge, Validate, Values,
+    ValuesFromGitLog,
+};
 use anyhow::anyhow;
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
 use std::env;
+use std::path::{Path, PathBuf};
 
-pub fn get() -> Result<CommitStructure, anyhow::Error> {
-    match find_git_bottle_config() {
-        None => Ok(default_config()),
-        Some(filename) => {
-            let file = std::fs::File::open(&filename)
-                .map_err(|e| anyhow!("Could not open file: {}", e))?;
-            serde_yaml::from_reader(file)
-                .map_err(|e| anyhow!("Malformed config file {}: {}", filename, e))
+/// Set to the path of a configuration file overriding all the others.
+pub const CONFIG_ENV: &str = "GIT_BOTTLE_CONFIG";
+
+const CONFIG_EXTENSIONS: [&str; 3] = ["yml", "toml", "json"];
+const DEFAULTS: &str = "built-in defaults";
+const GIT_CONFIG: &str = "git config";
+
+/// A configuration file, or the built-in defaults.
+pub struct Layer {
+    pub origin: String,
+    pub value: Value,
+}
+
+/// The layers which set each top-level key and each trailer of the merged configuration.
+#[derive(Debug, Default, PartialEq)]
+pub struct Origins {
+    pub keys: BTreeMap<String, Vec<String>>,
+    pub trailers: Vec<String>,
+}
+
+pub fn get(git: &impl Git) -> Result<CommitStructure, anyhow::Error> {
+    Ok(load(git)?.0)
+}
+
+/// The effective configuration, with the origins of its keys.
+pub fn load(git: &impl Git) -> Result<(CommitStructure, Origins), anyhow::Error> {
+    let (merged, mut origins) = merge(layers(git)?)?;
+    let structure =
+        serde_yaml::from_value(merged).map_err(|e| anyhow!("Malformed configuration: {}", e))?;
+    let structure = with_user_authors(structure, &mut origins)?;
+    message::check_template(&structure)?;
+    Ok((structure, origins))
+}
+
+/// Where a configuration layer comes from.
+enum Source {
+    File(PathBuf),
+    GitConfig,
+}
+
+/// The configuration sources, from the lowest to the highest precedence: the user configuration,
+/// the `.git-bottle.*` file of each ancestor directory (outermost first), the `bottle.*` git
+/// config, the private `git-bottle.*` file of the git directory and the file of
+/// `GIT_BOTTLE_CONFIG`.
+fn sources(git: &impl Git) -> Result<Vec<Source>, anyhow::Error> {
+    let mut paths: Vec<PathBuf> = vec![];
+    if let Some(dir) = user_config_dir() {
+        paths.extend(find_config_file(&dir, "config")?);
+    }
+    paths.extend(find_git_bottle_configs()?);
+    let mut sources: Vec<Source> = paths.into_iter().map(Source::File).collect();
+    sources.push(Source::GitConfig);
+    if let Ok(path) = git.git_path("git-bottle.yml") {
+        if let Some(dir) = path.parent() {
+            sources.extend(find_config_file(dir, "git-bottle")?.map(Source::File));
+        }
+    }
+    if let Some(path) = env::var_os(CONFIG_ENV).map(PathBuf::from) {
+        if !path.exists() {
+            return Err(anyhow!(
+                "Could not find the file {} of {}",
+                path.display(),
+                CONFIG_ENV
+            ));
+        }
+        sources.push(Source::File(path));
+    }
+    Ok(sources)
+}
+
+/// The configuration files in use, from the lowest to the highest precedence.
+pub fn paths(git: &impl Git) -> Result<Vec<PathBuf>, anyhow::Error> {
+    Ok(sources(git)?
+        .into_iter()
+        .filter_map(|source| match source {
+            Source::File(path) => Some(path),
+            Source::GitConfig => None,
+        })
+        .collect())
+}
+
+/// The built-in defaults followed by the configuration sources in use.
+pub fn layers(git: &impl Git) -> Result<Vec<Layer>, anyhow::Error> {
+    let defaults = serde_yaml::to_value(default_config()).map_err(|e| anyhow!(e))?;
+    let mut layers = vec![Layer {
+        origin: DEFAULTS.to_string(),
+        value: defaults,
+    }];
+    for source in sources(git)? {
+        match source {
+            Source::File(path) => layers.push(Layer {
+                origin: path.display().to_string(),
+                value: read_layer(&path)?,
+            }),
+            Source::GitConfig => {
+                let entries = git.config_get_regexp(r"^bottle\.")?;
+                if let Some(value) = git_config_layer(&entries)? {
+                    layers.push(Layer {
+                        origin: GIT_CONFIG.to_string(),
+                        value,
+                    });
+                }
+            }
+        }
+    }
+    Ok(layers)
+}
+
+/// Reads a configuration file, upgrading it in memory to the latest version of the format.
+fn read_layer(path: &Path) -> Result<Value, anyhow::Error> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Could not read file {}: {}", path.display(), e))?;
+    validate(path, &content)?;
+    let (value, changes) = migrate::migrate(parse(path, &content)?)
+        .map_err(|e| anyhow!("Unsupported config file {}: {}", path.display(), e))?;
+    for change in changes {
+        eprintln!(
+            "warning: {}: {}, run `git bottle config migrate` to upgrade it",
+            path.display(),
+            change
+        );
+    }
+    Ok(value)
+}
+
+/// The content of a configuration file, an empty file being an empty mapping.
+pub fn parse(path: &Path, content: &str) -> Result<Value, anyhow::Error> {
+    let value: Value = match is_toml(path) {
+        true => toml::from_str(content)
+            .map_err(|e| anyhow!("Malformed config file {}: {}", path.display(), e))?,
+        false => serde_yaml::from_str(content)
+            .map_err(|e| anyhow!("Malformed config file {}: {}", path.display(), e))?,
+    };
+    match value {
+        Value::Null => Ok(Value::Mapping(Mapping::new())),
+        value => Ok(value),
+    }
+}
+
+pub fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "toml")
+}
+
+/// Checks the content of a YAML, JSON or TOML configuration file, describing the first error with
+/// its location and, for an unknown key or type, the closest known one. A file written for an
+/// older version of the format is checked once upgraded, without locations.
+pub fn validate(path: &Path, content: &str) -> Result<(), anyhow::Error> {
+    let origin = path.display().to_string();
+    if let Ok(value) = parse(path, content) {
+        let (migrated, changes) = migrate::migrate(value)
+            .map_err(|e| anyhow!("{}", describe(&origin, None, &e.to_string())))?;
+        if !changes.is_empty() {
+            return match serde_yaml::from_value::<CommitStructure>(migrated) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(anyhow!("{}", describe(&origin, None, &e.to_string()))),
+            };
+        }
+    }
+
+    if is_toml(path) {
+        return match toml::from_str::<CommitStructure>(content) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let location = e.span().map(|span| line_column(content, span.start));
+                Err(anyhow!("{}", describe(&origin, location, e.message())))
+            }
+        };
+    }
+
+    // JSON is parsed as YAML, which is a superset of it
+    if let Ok(Value::Null) = serde_yaml::from_str::<Value>(content) {
+        return Ok(());
+    }
+    match serde_yaml::from_str::<CommitStructure>(content) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let message = e.to_string();
+            let message = match message.rfind(" at line ") {
+                Some(index) => &message[..index],
+                None => &message,
+            };
+            let location = e.location().map(|l| (l.line(), l.column()));
+            Err(anyhow!("{}", describe(&origin, location, message)))
+        }
+    }
+}
+
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line, column)
+}
+
+fn describe(origin: &str, location: Option<(usize, usize)>, message: &str) -> String {
+    let location = location
+        .map(|(line, column)| format!(":{}:{}", line, column))
+        .unwrap_or_default();
+    match suggestion(message) {
+        Some(suggestion) => format!(
+            "{}{}: {}, did you mean `{}`?",
+            origin, location, message, suggestion
+        ),
+        None => format!("{}{}: {}", origin, location, message),
+    }
+}
+
+/// The closest expected value of an `unknown field` or `unknown variant` error.
+fn suggestion(message: &str) -> Option<String> {
+    let unknown = ["unknown field `", "unknown variant `"]
+        .iter()
+        .find_map(|prefix| message.split_once(prefix))
+        .map(|(_, rest)| rest)?;
+    let (unknown, rest) = unknown.split_once('`')?;
+    let (_, expected) = rest.split_once("expected")?;
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|candidate| (distance(unknown, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= 2.max(candidate.len() / 3))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+/// The Levenshtein distance between two strings.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Merges the layers: mappings are merged key by key, other values are replaced, and the
+/// `trailers` of a layer replace, are appended to, or override by name the ones below it,
+/// according to its `trailers_merge`.
+pub fn merge(layers: Vec<Layer>) -> Result<(Value, Origins), anyhow::Error> {
+    let mut merged = Mapping::new();
+    let mut origins = Origins::default();
+    for layer in layers {
+        let Value::Mapping(mut mapping) = layer.value else {
+            continue;
+        };
+        let strategy = match mapping.remove("trailers_merge") {
+            None => TrailersMerge::Replace,
+            Some(strategy) => serde_yaml::from_value(strategy).map_err(|_| {
+                anyhow!(
+                    "Malformed config file {}: trailers_merge must be replace, append or override",
+                    layer.origin
+                )
+            })?,
+        };
+        if let Some(trailers) = mapping.remove("trailers") {
+            let trailers = sequence(trailers, &layer.origin)?;
+            let mut below = match merged.remove("trailers") {
+                Some(Value::Sequence(below)) if strategy != TrailersMerge::Replace => below,
+                _ => {
+                    origins.trailers.clear();
+                    vec![]
+                }
+            };
+            for trailer in trailers {
+                let index = match strategy {
+                    TrailersMerge::Override => below.iter().position(|t| same_name(t, &trailer)),
+                    _ => None,
+                };
+                match index {
+                    Some(index) => {
+                        below[index] = trailer;
+                        origins.trailers[index] = layer.origin.clone();
+                    }
+                    None => {
+                        below.push(trailer);
+                        origins.trailers.push(layer.origin.clone());
+                    }
+                }
+            }
+            merged.insert(Value::from("trailers"), Value::Sequence(below));
+            set_origin(
+                &mut origins,
+                "trailers",
+                &layer.origin,
+                strategy != TrailersMerge::Replace,
+            );
+        }
+        for (key, value) in mapping {
+            let merges = matches!(
+                (merged.get(&key), &value),
+                (Some(Value::Mapping(_)), Value::Mapping(_))
+            );
+            if let Some(key) = key.as_str() {
+                set_origin(&mut origins, key, &layer.origin, merges);
+            }
+            merge_value(&mut merged, key, value);
+        }
+    }
+    Ok((Value::Mapping(merged), origins))
+}
+
+fn merge_value(merged: &mut Mapping, key: Value, value: Value) {
+    match (merged.get_mut(&key), value) {
+        (Some(Value::Mapping(below)), Value::Mapping(above)) => {
+            for (key, value) in above {
+                merge_value(below, key, value);
+            }
+        }
+        (_, value) => {
+            merged.insert(key, value);
+        }
+    }
+}
+
+fn set_origin(origins: &mut Origins, key: &str, origin: &str, merges: bool) {
+    let key_origins = origins.keys.entry(key.to_string()).or_default();
+    if !merges {
+        key_origins.clear();
+    }
+    key_origins.push(origin.to_string());
+}
+
+fn sequence(value: Value, origin: &str) -> Result<Vec<Value>, anyhow::Error> {
+    match value {
+        Value::Sequence(sequence) => Ok(sequence),
+        _ => Err(anyhow!(
+            "Malformed config file {}: trailers: expected a sequence",
+            origin
+        )),
+    }
+}
+
+fn same_name(a: &Value, b: &Value) -> bool {
+    match (
+        a.get("name").and_then(Value::as_str),
+        b.get("name").and_then(Value::as_str),
+    ) {
+        (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+        _ => false,
+    }
+}
+
+/// Adds the authors of the user roster which are not in the configuration.
+fn with_user_authors(
+    mut structure: CommitStructure,
+    origins: &mut Origins,
+) -> Result<CommitStructure, anyhow::Error> {
+    if let Some(path) = authors::user_roster_path() {
+        let roster = authors::load(&path)?;
+        if !roster.authors.is_empty() {
+            set_origin(origins, "authors", &path.display().to_string(), true);
+        }
+        for (handle, identity) in roster.authors {
+            structure.authors.entry(handle).or_insert(identity);
+        }
+    }
+    Ok(structure)
+}
+
+/// The JSON Schema of a configuration file.
+pub fn schema() -> Result<String, anyhow::Error> {
+    serde_json::to_string_pretty(&schemars::schema_for!(CommitStructure)).map_err(|e| anyhow!(e))
+}
+
+/// The configuration as YAML, each top-level key and each trailer preceded by its origin.
+pub fn show(structure: &CommitStructure, origins: &Origins) -> Result<String, anyhow::Error> {
+    let Value::Mapping(mapping) = serde_yaml::to_value(structure).map_err(|e| anyhow!(e))? else {
+        return Err(anyhow!("The configuration is not a mapping"));
+    };
+
+    let mut out = String::new();
+    for (key, value) in mapping {
+        let name = key.as_str().unwrap_or_default().to_string();
+        if let Some(key_origins) = origins.keys.get(&name) {
+            out.push_str(&format!("# {}\n", key_origins.join(", ")));
+        }
+        match value {
+            Value::Sequence(trailers) if name == "trailers" && !trailers.is_empty() => {
+                out.push_str("trailers:\n");
+                for (i, trailer) in trailers.into_iter().enumerate() {
+                    if let Some(origin) = origins.trailers.get(i) {
+                        out.push_str(&format!("# {}\n", origin));
+                    }
+                    out.push_str(&serde_yaml::to_string(&vec![trailer]).map_err(|e| anyhow!(e))?);
+                }
+            }
+            value => {
+                let single = Mapping::from_iter([(key, value)]);
+                out.push_str(&serde_yaml::to_string(&single).map_err(|e| anyhow!(e))?);
+            }
         }
     }
+    Ok(out)
 }
 
-fn default_config() -> CommitStructure {
+pub fn default_config() -> CommitStructure {
     CommitStructure {
         trailers: vec![Trailer::MultiSelect(MultiSelectTrailer {
             name: "Co-authored-by".to_string(),
+            when: None,
+            validate: Validate::default(),
+            default: None,
             values: Values::FromGitLog(ValuesFromGitLog {
                 max_count: None,
                 format_strings: vec![
                     "%an <%ae>".to_string(),
                     "%(trailers:key=Co-authored-by,valueonly=true)".to_string(),
                 ],
+                order: Order::default(),
+                half_life: None,
+                exclude_self: true,
             }),
         })],
+        ..Default::default()
     }
 }
 
-fn find_git_bottle_config() -> Option<String> {
-    let current_dir = env::current_dir().ok()?;
+/// The directory of the user configuration, `$XDG_CONFIG_HOME/git-bottle`.
+fn user_config_dir() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("git-bottle"))
+}
+
+/// The `.git-bottle.*` files of the current directory and its ancestors, outermost first.
+fn find_git_bottle_configs() -> Result<Vec<PathBuf>, anyhow::Error> {
+    let Ok(current_dir) = env::current_dir() else {
+        return Ok(vec![]);
+    };
 
+    let mut paths: Vec<PathBuf> = vec![];
     for ancestor in current_dir.ancestors() {
-        let file_path = ancestor.join(".git-bottle.yml");
-        if file_path.exists() {
-            return Some(file_path.to_string_lossy().into_owned());
+        paths.extend(find_config_file(ancestor, ".git-bottle")?);
+    }
+    paths.reverse();
+    Ok(paths)
+}
+
+/// The configuration file of the directory named `stem` with a supported extension, if any.
+fn find_config_file(dir: &Path, stem: &str) -> Result<Option<PathBuf>, anyhow::Error> {
+    let found: Vec<PathBuf> = CONFIG_EXTENSIONS
+        .iter()
+        .map(|extension| dir.join(format!("{}.{}", stem, extension)))
+        .filter(|path| path.exists())
+        .collect();
+    match found.len() {
+        0 | 1 => Ok(found.into_iter().next()),
+        _ => Err(anyhow!(
+            "Several configuration files in {}: {}, keep only one",
+            dir.display(),
+            found
+                .iter()
+                .map(|p| p.file_name().unwrap_or_default().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// The layer of the `bottle.trailer.<name>.<key>` git config entries, overriding the trailers
+/// with the same name.
+fn git_config_layer(entries: &[(String, String)]) -> Result<Option<Value>, anyhow::Error> {
+    let mut trailers: Vec<(String, Mapping)> = vec![];
+    for (key, value) in entries {
+        let Some((name, variable)) = key
+            .strip_prefix("bottle.trailer.")
+            .and_then(|rest| rest.rsplit_once('.'))
+        else {
+            return Err(anyhow!(
+                "Unknown git config {}, expected bottle.trailer.<name>.<key>",
+                key
+            ));
+        };
+        let index = match trailers.iter().position(|(n, _)| n == name) {
+            Some(index) => index,
+            None => {
+                trailers.push((name.to_string(), Mapping::new()));
+                trailers.len() - 1
+            }
+        };
+        let trailer = &mut trailers[index].1;
+        let variable = variable.to_lowercase();
+        match variable.as_str() {
+            "type" | "pattern" => {
+                trailer.insert(Value::from(variable), Value::from(value.as_str()));
+            }
+            "required" => {
+                trailer.insert(Value::from(variable), Value::from(git_bool(key, value)?));
+            }
+            "maxcount" => {
+                let max_count: i32 = value
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid git config {}: expected a number", key))?;
+                trailer.insert(Value::from(variable), Value::from(max_count));
+            }
+            "options" | "format" => {
+                let values = trailer
+                    .entry(Value::from(variable))
+                    .or_insert_with(|| Value::Sequence(vec![]));
+                if let Value::Sequence(values) = values {
+                    values.push(Value::from(value.as_str()));
+                }
+            }
+            _ => {
+                return Err(anyhow!(
+                    "Unknown git config {}, expected one of type, required, pattern, options, format, maxCount",
+                    key
+                ))
+            }
+        }
+    }
+    if trailers.is_empty() {
+        return Ok(None);
+    }
+
+    let trailers: Vec<Value> = trailers
+        .into_iter()
+        .map(|(name, mut entries)| {
+            let options = entries.remove("options");
+            let format = entries.remove("format");
+            let max_count = entries.remove("maxcount");
+            let kind = match (entries.remove("type"), &options, &format) {
+                (Some(kind), _, _) => kind,
+                (None, None, None) => Value::from("text"),
+                _ => Value::from("multi_select"),
+            };
+
+            let mut trailer = Mapping::new();
+            trailer.insert(Value::from("type"), kind);
+            trailer.insert(Value::from("name"), Value::from(name));
+            let mut validate = Mapping::new();
+            for key in ["required", "pattern"] {
+                if let Some(value) = entries.remove(key) {
+                    validate.insert(Value::from(key), value);
+                }
+            }
+            if !validate.is_empty() {
+                trailer.insert(Value::from("validate"), Value::Mapping(validate));
+            }
+            let mut values = Mapping::new();
+            if let Some(options) = options {
+                values.insert(Value::from("type"), Value::from("from_options"));
+                values.insert(Value::from("options"), options);
+            } else if let Some(format) = format {
+                values.insert(Value::from("type"), Value::from("from_git_log"));
+                values.insert(Value::from("format_strings"), format);
+                if let Some(max_count) = max_count {
+                    values.insert(Value::from("max_count"), max_count);
+                }
+            }
+            if !values.is_empty() {
+                trailer.insert(Value::from("values"), Value::Mapping(values));
+            }
+            Value::Mapping(trailer)
+        })
+        .collect();
+
+    let layer = Value::Mapping(Mapping::from_iter([
+        (Value::from("trailers_merge"), Value::from("override")),
+        (Value::from("trailers"), Value::Sequence(trailers)),
+    ]));
+    serde_yaml::from_value::<CommitStructure>(layer.clone())
+        .map_err(|e| anyhow!("Malformed {} bottle.trailer.*: {}", GIT_CONFIG, e))?;
+    Ok(Some(layer))
+}
+
+/// A git config boolean, e.g. `true`, `yes`, `on` or `1`.
+fn git_bool(key: &str, value: &str) -> Result<bool, anyhow::Error> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" | "" => Ok(false),
+        _ => Err(anyhow!("Invalid git config {}: expected a boolean", key)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::{git_config_layer, merge, schema, validate, Layer};
+    use serde_yaml::Value;
+    use std::path::Path;
+
+    fn layer(yaml: &str) -> Layer {
+        named_layer("test", yaml)
+    }
+
+    fn named_layer(origin: &str, yaml: &str) -> Layer {
+        Layer {
+            origin: origin.to_string(),
+            value: serde_yaml::from_str(yaml).unwrap(),
         }
     }
 
-    None
+    fn names(merged: &Value) -> Vec<&str> {
+        merged["trailers"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect()
+    }
+
+    const BASE: &str = "
+trailers:
+  - {type: text, name: Issue}
+  - {type: text, name: Reviewed-by}
+authors:
+  jd: Jane Doe <jane.doe@example.org>
+";
+
+    #[test]
+    fn test_merge_replace() {
+        let merged = merge(vec![
+            layer(BASE),
+            layer("trailers: [{type: text, name: Refs}]"),
+        ])
+        .unwrap()
+        .0;
+
+        assert_eq!(names(&merged), vec!["Refs"]);
+    }
+
+    #[test]
+    fn test_merge_append() {
+        let merged = merge(vec![
+            layer(BASE),
+            layer("trailers_merge: append\ntrailers: [{type: text, name: Refs}]"),
+        ])
+        .unwrap()
+        .0;
+
+        assert_eq!(names(&merged), vec!["Issue", "Reviewed-by", "Refs"]);
+    }
+
+    #[test]
+    fn test_merge_override() {
+        let merged = merge(vec![
+            layer(BASE),
+            layer("trailers_merge: override\ntrailers: [{type: text, name: issue, validate: {required: true}}, {type: text, name: Refs}]"),
+        ])
+        .unwrap()
+        .0;
+
+        assert_eq!(names(&merged), vec!["issue", "Reviewed-by", "Refs"]);
+        assert_eq!(
+            merged["trailers"][0]["validate"]["required"],
+            Value::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_merge_mappings() {
+        let merged = merge(vec![
+            layer(BASE),
+            layer("authors:\n  js: Joe Shmoe <joe.shmoe@example.org>"),
+            layer(""),
+        ])
+        .unwrap()
+        .0;
+
+        assert_eq!(names(&merged), vec!["Issue", "Reviewed-by"]);
+        assert_eq!(merged["authors"].as_mapping().unwrap().len(), 2);
+        assert!(merged.get("trailers_merge").is_none());
+    }
+
+    #[test]
+    fn test_merge_origins() {
+        let (_, origins) = merge(vec![
+            named_layer("base", BASE),
+            named_layer(
+                "repo",
+                "trailers_merge: override\ntrailers: [{type: text, name: Issue}]\nauthors: {}",
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(origins.trailers, vec!["repo", "base"]);
+        assert_eq!(origins.keys["trailers"], vec!["base", "repo"]);
+        assert_eq!(origins.keys["authors"], vec!["base", "repo"]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(Path::new("test.yml"), BASE).is_ok());
+        assert!(validate(Path::new("test.yml"), "").is_ok());
+        assert_eq!(
+            validate(Path::new("test.yml"), "trailers:\n  - type: multiselect\n    name: X\n")
+                .unwrap_err()
+                .to_string(),
+            "test.yml:2:11: trailers[0].type: unknown variant `multiselect`, expected one of `text`, `select`, `multi_select`, did you mean `multi_select`?"
+        );
+        assert_eq!(
+            validate(Path::new("test.yml"), "trailer: []\n").unwrap_err().to_string(),
+            "test.yml:1:1: unknown field `trailer`, expected one of `version`, `trailers`, `trailers_merge`, `authors`, `identities`, `message`, did you mean `trailers`?"
+        );
+        assert!(validate(
+            Path::new("test.yml"),
+            "trailers:\n  - {type: text, name: X, validate: {required: true}}\n"
+        )
+        .is_ok());
+        assert_eq!(
+            validate(Path::new("test.yml"), "version: 2\n")
+                .unwrap_err()
+                .to_string(),
+            "test.yml: version 2 is not supported, the latest is 1: upgrade git-bottle"
+        );
+    }
+
+    #[test]
+    fn test_published_schema_is_up_to_date() {
+        assert_eq!(
+            format!("{}\n", schema().unwrap()),
+            include_str!("../docs/config/git-bottle.schema.json"),
+            "run `git bottle config schema > docs/config/git-bottle.schema.json`"
+        );
+    }
+
+    #[test]
+    fn test_validate_toml_and_json() {
+        let toml = "[[trailers]]\ntype = \"text\"\nname = \"Issue\"\n";
+        assert!(validate(Path::new("test.toml"), toml).is_ok());
+        assert_eq!(
+            validate(Path::new("test.toml"), "[[trailers]]\ntype = \"txt\"\nname = \"Issue\"\n")
+                .unwrap_err()
+                .to_string(),
+            "test.toml:2:8: unknown variant `txt`, expected one of `text`, `select`, `multi_select`, did you mean `text`?"
+        );
+        let json = r#"{"trailers": [{"type": "text", "name": "Issue"}]}"#;
+        assert!(validate(Path::new("test.json"), json).is_ok());
+    }
+
+    #[test]
+    fn test_git_config_layer() {
+        let entries: Vec<(String, String)> = [
+            ("bottle.trailer.Issue.type", "select"),
+            (
+                "bottle.trailer.Issue.format",
+                "%(trailers:key=Issue,valueonly=true)",
+            ),
+            ("bottle.trailer.Issue.maxcount", "10"),
+            (
+                "bottle.trailer.Reviewed-by.options",
+                "Jane Doe <jane.doe@example.org>",
+            ),
+            (
+                "bottle.trailer.Reviewed-by.options",
+                "Joe Shmoe <joe.shmoe@example.org>",
+            ),
+            ("bottle.trailer.Refs.required", "yes"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let layer = git_config_layer(&entries).unwrap().unwrap();
+
+        assert_eq!(
+            layer,
+            serde_yaml::from_str::<Value>(
+                "
+trailers_merge: override
+trailers:
+  - type: select
+    name: Issue
+    values: {type: from_git_log, format_strings: ['%(trailers:key=Issue,valueonly=true)'], max_count: 10}
+  - type: multi_select
+    name: Reviewed-by
+    values: {type: from_options, options: [Jane Doe <jane.doe@example.org>, Joe Shmoe <joe.shmoe@example.org>]}
+  - type: text
+    name: Refs
+    validate: {required: true}
+"
+            )
+            .unwrap()
+        );
+        assert!(git_config_layer(&[]).unwrap().is_none());
+    }
 }
@@ -0,0 +1,339 @@
+use crate::git::Git;
+use crate::message::{self, Answers};
+use crate::prompt::Prompt;
+use crate::run::Draft;
+use crate::structure::{CommitType, Message, Scopes, Validate};
+use anyhow::anyhow;
+use regex::Regex;
+
+pub const BREAKING_CHANGE: &str = "BREAKING CHANGE";
+const NO_SCOPE: &str = "(none)";
+
+/// The subject of a Conventional Commits message, `type(scope)!: description`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Subject {
+    pub kind: String,
+    pub scope: String,
+    pub breaking: bool,
+    pub description: String,
+}
+
+impl Subject {
+    pub fn parse(subject: &str) -> Option<Subject> {
+        let regex = Regex::new(r"^([\w-]+)(?:\(([^)]*)\))?(!)?: (.*)$").unwrap();
+        let captures = regex.captures(subject.trim())?;
+        Some(Subject {
+            kind: captures[1].to_string(),
+            scope: captures.get(2).map_or("", |m| m.as_str()).to_string(),
+            breaking: captures.get(3).is_some(),
+            description: captures[4].to_string(),
+        })
+    }
+
+    pub fn format(&self) -> String {
+        let scope = match self.scope.is_empty() {
+            true => String::new(),
+            false => format!("({})", self.scope),
+        };
+        let breaking = if self.breaking { "!" } else { "" };
+        format!("{}{}{}: {}", self.kind, scope, breaking, self.description)
+    }
+}
+
+/// Prompts for a Conventional Commits message, returning the answers with its `BREAKING CHANGE`
+/// trailer, if any.
+pub fn prompt_message(
+    prompt: &impl Prompt,
+    git: &impl Git,
+    config: &Message,
+    draft: &Draft,
+) -> Result<(Answers, Vec<(String, String)>), anyhow::Error> {
+    let (subject, body) = draft
+        .message
+        .split_once('\n')
+        .unwrap_or((&draft.message, ""));
+    let current = Subject::parse(subject).unwrap_or_default();
+    let current_note = draft
+        .trailers
+        .iter()
+        .find(|(key, _)| is_breaking_change(key))
+        .map(|(_, value)| value.as_str());
+
+    let types = match config.types.is_empty() {
+        true => default_types(),
+        false => config.types.clone(),
+    };
+    let labels: Vec<String> = types
+        .iter()
+        .map(|t| match t.description.is_empty() {
+            true => t.name.clone(),
+            false => format!("{}: {}", t.name, t.description),
+        })
+        .collect();
+    let default = types.iter().position(|t| t.name == current.kind);
+    let label = prompt.select(
+        "Type:",
+        labels.clone(),
+        false,
+        default,
+        &Validate::default(),
+    )?;
+    let kind = labels
+        .iter()
+        .position(|l| *l == label)
+        .map_or(label, |index| types[index].name.clone());
+
+    let scopes = match &config.scopes {
+        Some(scopes) => find_scopes(git, scopes)?,
+        None => vec![],
+    };
+    let scope = if scopes.is_empty() {
+        prompt.text("Scope (optional):", &current.scope, &Validate::default())?
+    } else {
+        let current = match current.scope.as_str() {
+            "" => NO_SCOPE,
+            scope => scope,
+        };
+        let mut options = vec![NO_SCOPE.to_string()];
+        options.extend(scopes);
+        if !options.iter().any(|o| o == current) {
+            options.push(current.to_string());
+        }
+        let default = options.iter().position(|o| o == current);
+        match prompt.select("Scope:", options, true, default, &Validate::default())? {
+            scope if scope == NO_SCOPE => String::new(),
+            scope => scope,
+        }
+    };
+
+    let breaking = prompt.confirm(
+        "Breaking change?",
+        current.breaking || current_note.is_some(),
+    )?;
+    let prefix = Subject {
+        kind: kind.clone(),
+        scope: scope.trim().to_string(),
+        breaking,
+        description: String::new(),
+    }
+    .format();
+    let description = message::prompt_subject(
+        prompt,
+        config,
+        "Description:",
+        &current.description,
+        prefix.chars().count(),
+    )?;
+    let body = message::prompt_body(prompt, git, config, body.trim())?;
+
+    let mut trailers = vec![];
+    if breaking {
+        let key = format!("{}:", BREAKING_CHANGE);
+        let note = prompt.text(&key, current_note.unwrap_or_default(), &Validate::default())?;
+        if !note.is_empty() {
+            trailers.push((BREAKING_CHANGE.to_string(), note));
+        }
+    }
+
+    let scope = scope.trim().to_string();
+    let subject = Subject {
+        kind: kind.clone(),
+        scope: scope.clone(),
+        breaking,
+        description: description.clone(),
+    }
+    .format();
+    let answers = Answers {
+        subject,
+        summary: description,
+        kind,
+        scope,
+        body,
+    };
+    Ok((answers, trailers))
+}
+
+/// The answers of a Conventional Commits message given on the command line, checking its type
+/// and the length of its subject.
+pub fn given(config: &Message, given: &str) -> Result<Answers, anyhow::Error> {
+    let (subject, body) = given.trim().split_once('\n').unwrap_or((given.trim(), ""));
+    let parsed = Subject::parse(subject).ok_or_else(|| {
+        anyhow!(
+            "The message \"{}\" is not a Conventional Commits subject, e.g. \"feat(api): add the v2 endpoints\"",
+            subject
+        )
+    })?;
+    let types = match config.types.is_empty() {
+        true => default_types(),
+        false => config.types.clone(),
+    };
+    if !types.iter().any(|t| t.name == parsed.kind) {
+        let names: Vec<&str> = types.iter().map(|t| t.name.as_str()).collect();
+        return Err(anyhow!(
+            "Unknown type {}, expected one of: {}",
+            parsed.kind,
+            names.join(", ")
+        ));
+    }
+    let prefix = parsed.format().chars().count() - parsed.description.chars().count();
+    message::check_subject(config, prefix, &parsed.description)?;
+    Ok(Answers {
+        subject: parsed.format(),
+        summary: parsed.description,
+        kind: parsed.kind,
+        scope: parsed.scope,
+        body: body.trim().to_string(),
+    })
+}
+
+/// Whether the trailer is the `BREAKING CHANGE` footer, also spelled `BREAKING-CHANGE`.
+pub fn is_breaking_change(key: &str) -> bool {
+    key == BREAKING_CHANGE || key == "BREAKING-CHANGE"
+}
+
+fn find_scopes(git: &impl Git, scopes: &Scopes) -> Result<Vec<String>, anyhow::Error> {
+    let mut found: Vec<String> = match scopes {
+        Scopes::FromOptions(s) => return Ok(s.options.clone()),
+        Scopes::FromGitLog(s) => git
+            .log("%s", &s.max_count)?
+            .iter()
+            .filter_map(|subject| Subject::parse(subject))
+            .map(|subject| subject.scope)
+            .filter(|scope| !scope.is_empty())
+            .collect(),
+        Scopes::FromStagedPaths(_) => git
+            .staged_files()?
+            .iter()
+            .filter_map(|path| path.split_once('/'))
+            .map(|(dir, _)| dir.to_string())
+            .collect(),
+    };
+    found.sort();
+    found.dedup();
+    Ok(found)
+}
+
+/// The types of the Conventional Commits specification and of the Angular convention.
+fn default_types() -> Vec<CommitType> {
+    [
+        ("feat", "A new feature"),
+        ("fix", "A bug fix"),
+        ("docs", "Documentation only changes"),
+        (
+            "style",
+            "Changes that do not affect the meaning of the code",
+        ),
+        (
+            "refactor",
+            "A code change that neither fixes a bug nor adds a feature",
+        ),
+        ("perf", "A code change that improves performance"),
+        ("test", "Adding missing tests or correcting existing tests"),
+        (
+            "build",
+            "Changes that affect the build system or external dependencies",
+        ),
+        ("ci", "Changes to the CI configuration files and scripts"),
+        ("chore", "Other changes that don't modify src or test files"),
+        ("revert", "Reverts a previous commit"),
+    ]
+    .iter()
+    .map(|(name, description)| CommitType {
+        name: name.to_string(),
+        description: description.to_string(),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::conventional::{prompt_message, Subject};
+    use crate::git::MockGit;
+    use crate::prompt::MockPrompt;
+    use crate::run::Draft;
+    use crate::structure::{Message, MessageStyle, Scopes, ScopesFromStagedPaths};
+    use mockall::predicate::{always, eq};
+
+    #[test]
+    fn test_subject() {
+        let subject = Subject::parse("feat(api)!: drop the v1 endpoints").unwrap();
+
+        assert_eq!(
+            subject,
+            Subject {
+                kind: "feat".to_string(),
+                scope: "api".to_string(),
+                breaking: true,
+                description: "drop the v1 endpoints".to_string(),
+            }
+        );
+        assert_eq!(subject.format(), "feat(api)!: drop the v1 endpoints");
+        assert_eq!(Subject::parse("Drop the v1 endpoints"), None);
+    }
+
+    #[test]
+    fn test_prompt_message() {
+        let message = Message {
+            style: MessageStyle::Conventional,
+            scopes: Some(Scopes::FromStagedPaths(ScopesFromStagedPaths {})),
+            ..Default::default()
+        };
+
+        let mut prompt: MockPrompt = MockPrompt::new();
+        let mut git: MockGit = MockGit::new();
+
+        git.expect_staged_files().returning(|| {
+            Ok(vec![
+                "api/v1.rs".to_string(),
+                "api/v2.rs".to_string(),
+                "README.md".to_string(),
+            ])
+        });
+        prompt
+            .expect_select()
+            .with(eq("Type:"), always(), eq(false), eq(None), always())
+            .returning(|_, options, _, _, _| Ok(options[0].clone()));
+        prompt
+            .expect_select()
+            .with(
+                eq("Scope:"),
+                eq(vec!["(none)".to_string(), "api".to_string()]),
+                eq(true),
+                eq(Some(0)),
+                always(),
+            )
+            .returning(|_, _, _, _, _| Ok("api".to_string()));
+        prompt
+            .expect_confirm()
+            .with(eq("Breaking change?"), eq(false))
+            .returning(|_, _| Ok(true));
+        prompt
+            .expect_subject()
+            .with(eq("Description:"), eq(""), always(), always())
+            .returning(|_, _, _, _| Ok("drop the v1 endpoints".to_string()));
+        git.expect_editor().returning(|| Ok("vim".to_string()));
+        prompt
+            .expect_editor()
+            .with(eq("Body (optional):"), eq(""), eq("vim"))
+            .returning(|_, _, _| Ok("The v1 endpoints were deprecated a year ago.\n".to_string()));
+        prompt
+            .expect_text()
+            .with(eq("BREAKING CHANGE:"), eq(""), always())
+            .returning(|_, _, _| Ok("the v1 clients must upgrade".to_string()));
+
+        let (answers, trailers) =
+            prompt_message(&prompt, &git, &message, &Draft::default()).unwrap();
+
+        assert_eq!(
+            answers.message(),
+            "feat(api)!: drop the v1 endpoints\n\nThe v1 endpoints were deprecated a year ago."
+        );
+        assert_eq!(
+            trailers,
+            vec![(
+                "BREAKING CHANGE".to_string(),
+                "the v1 clients must upgrade".to_string()
+            )]
+        );
+    }
+}
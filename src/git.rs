@@ -1,35 +1,61 @@
 use anyhow::anyhow;
+use std::path::PathBuf;
 use std::process::{Command, Stdio};
 use std::str;
 
+/// Set while `git-bottle` runs `git commit`, so that its own hooks don't prompt twice.
+pub const RUNNING_ENV: &str = "GIT_BOTTLE_RUNNING";
+
 #[cfg_attr(test, mockall::automock)]
 pub trait Git {
-    fn commit(&self, message: &str) -> Result<String, anyhow::Error>;
+    fn commit(&self, message: &str, args: &[String]) -> Result<String, anyhow::Error>;
     fn log(
         &self,
         format_string: &str,
         max_count: &Option<i32>,
     ) -> Result<Vec<String>, anyhow::Error>;
+    fn log_entries(
+        &self,
+        format_string: &str,
+        max_count: &Option<i32>,
+    ) -> Result<Vec<LogEntry>, anyhow::Error>;
+    fn check_mailmap(&self, contacts: &[String]) -> Result<Vec<String>, anyhow::Error>;
+    fn config_get(&self, key: &str) -> Result<Option<String>, anyhow::Error>;
+    fn config_get_regexp(&self, pattern: &str) -> Result<Vec<(String, String)>, anyhow::Error>;
+    fn git_path(&self, path: &str) -> Result<PathBuf, anyhow::Error>;
+    fn top_level(&self) -> Result<PathBuf, anyhow::Error>;
+    fn current_branch(&self) -> Result<Option<String>, anyhow::Error>;
+    fn staged_files(&self) -> Result<Vec<String>, anyhow::Error>;
+    fn editor(&self) -> Result<String, anyhow::Error>;
+    fn commits(&self, range: &str) -> Result<Vec<Commit>, anyhow::Error>;
+    fn message(&self, rev: &str) -> Result<String, anyhow::Error>;
+    fn reword(&self, rev: &str, message: &str) -> Result<String, anyhow::Error>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commit {
+    pub sha: String,
+    pub message: String,
+    /// Whether the commit has more than one parent
+    pub merge: bool,
+}
+
+/// A value found in the git log, with the author date of its commit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub timestamp: u64,
+    pub value: String,
 }
 
 pub struct GitCommand {}
 
 impl Git for GitCommand {
-    fn commit(&self, message: &str) -> Result<String, anyhow::Error> {
-        let output = Command::new("git")
+    fn commit(&self, message: &str, args: &[String]) -> Result<String, anyhow::Error> {
+        run(Command::new("git")
             .current_dir(".")
-            .args(["commit", "-m", &message])
-            .output()
-            .map_err(|e| anyhow!(e))?;
-
-        return if output.status.success() {
-            let out = str::from_utf8(&output.stdout).unwrap_or_default();
-            return Ok(out.to_string());
-        } else {
-            let out = str::from_utf8(&output.stdout).unwrap_or_default();
-            let err = str::from_utf8(&output.stderr).unwrap_or_default();
-            Err(anyhow!("{}\n{}", out, err))
-        };
+            .args(["commit", "-m", message])
+            .args(args)
+            .env(RUNNING_ENV, "1"))
     }
 
     fn log(
@@ -58,10 +84,256 @@ impl Git for GitCommand {
         lines.sort();
         lines.dedup();
 
-        return Ok(lines
+        Ok(lines
             .into_iter()
             .map(|l| l.trim().to_string())
             .filter(|l| !l.is_empty())
-            .collect::<Vec<String>>());
+            .collect::<Vec<String>>())
+    }
+
+    fn log_entries(
+        &self,
+        format_string: &str,
+        max_count: &Option<i32>,
+    ) -> Result<Vec<LogEntry>, anyhow::Error> {
+        let args: Vec<String> = vec![
+            Some("log".to_string()),
+            max_count.map(|d| format!("--max-count={}", d)),
+            Some(format!("--format=%at%x00{}%x1e", format_string)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        // one record per commit, newest first; a format string may expand to several lines
+        let out = run(Command::new("git").args(&args))?;
+        Ok(out
+            .split('\x1e')
+            .filter_map(|record| record.trim_start().split_once('\0'))
+            .flat_map(|(timestamp, values)| {
+                let timestamp = timestamp.parse().unwrap_or_default();
+                values
+                    .lines()
+                    .map(|l| l.trim())
+                    .filter(|l| !l.is_empty())
+                    .map(move |value| LogEntry {
+                        timestamp,
+                        value: value.to_string(),
+                    })
+                    .collect::<Vec<LogEntry>>()
+            })
+            .collect())
+    }
+
+    fn check_mailmap(&self, contacts: &[String]) -> Result<Vec<String>, anyhow::Error> {
+        if contacts.is_empty() {
+            return Ok(vec![]);
+        }
+        let out = run(Command::new("git").arg("check-mailmap").args(contacts))?;
+        Ok(out.lines().map(|l| l.to_string()).collect())
+    }
+
+    fn config_get(&self, key: &str) -> Result<Option<String>, anyhow::Error> {
+        let output = Command::new("git")
+            .args(["config", "--get", key])
+            .output()
+            .map_err(|e| anyhow!(e))?;
+
+        // git config exits with 1 when the key is not set
+        match output.status.code() {
+            Some(0) => {
+                let out = str::from_utf8(&output.stdout).map_err(|e| anyhow!(e))?;
+                Ok(Some(out.trim().to_string()))
+            }
+            Some(1) => Ok(None),
+            _ => {
+                let err = str::from_utf8(&output.stderr).unwrap_or_default();
+                Err(anyhow!("{}", err.trim()))
+            }
+        }
+    }
+
+    fn config_get_regexp(&self, pattern: &str) -> Result<Vec<(String, String)>, anyhow::Error> {
+        let output = Command::new("git")
+            .args(["config", "--get-regexp", pattern])
+            .output()
+            .map_err(|e| anyhow!(e))?;
+
+        // git config exits with 1 when no key matches
+        match output.status.code() {
+            Some(0) => {
+                let out = str::from_utf8(&output.stdout).map_err(|e| anyhow!(e))?;
+                Ok(out
+                    .lines()
+                    .map(|line| match line.split_once(' ') {
+                        Some((key, value)) => (key.to_string(), value.to_string()),
+                        None => (line.to_string(), "true".to_string()),
+                    })
+                    .collect())
+            }
+            Some(1) => Ok(vec![]),
+            _ => {
+                let err = str::from_utf8(&output.stderr).unwrap_or_default();
+                Err(anyhow!("{}", err.trim()))
+            }
+        }
+    }
+
+    fn git_path(&self, path: &str) -> Result<PathBuf, anyhow::Error> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--git-path", path])
+            .output()
+            .map_err(|e| anyhow!(e))?;
+
+        if output.status.success() {
+            let out = str::from_utf8(&output.stdout).map_err(|e| anyhow!(e))?;
+            Ok(PathBuf::from(out.trim()))
+        } else {
+            let err = str::from_utf8(&output.stderr).unwrap_or_default();
+            Err(anyhow!("{}", err.trim()))
+        }
+    }
+
+    fn top_level(&self) -> Result<PathBuf, anyhow::Error> {
+        let out = run(Command::new("git").args(["rev-parse", "--show-toplevel"]))?;
+        Ok(PathBuf::from(out.trim()))
+    }
+
+    fn current_branch(&self) -> Result<Option<String>, anyhow::Error> {
+        let output = Command::new("git")
+            .args(["symbolic-ref", "--quiet", "--short", "HEAD"])
+            .output()
+            .map_err(|e| anyhow!(e))?;
+
+        // git symbolic-ref exits with 1 when HEAD is detached
+        match output.status.code() {
+            Some(0) => {
+                let out = str::from_utf8(&output.stdout).map_err(|e| anyhow!(e))?;
+                Ok(Some(out.trim().to_string()))
+            }
+            Some(1) => Ok(None),
+            _ => {
+                let err = str::from_utf8(&output.stderr).unwrap_or_default();
+                Err(anyhow!("{}", err.trim()))
+            }
+        }
+    }
+
+    fn staged_files(&self) -> Result<Vec<String>, anyhow::Error> {
+        let out = run(Command::new("git").args(["diff", "--cached", "--name-only", "-z"]))?;
+        Ok(out
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(|path| path.to_string())
+            .collect())
+    }
+
+    /// The editor of git: `GIT_EDITOR`, `core.editor`, `VISUAL`, `EDITOR` or its default.
+    fn editor(&self) -> Result<String, anyhow::Error> {
+        let out = run(Command::new("git").args(["var", "GIT_EDITOR"]))?;
+        Ok(out.trim().to_string())
+    }
+
+    fn commits(&self, range: &str) -> Result<Vec<Commit>, anyhow::Error> {
+        let output = Command::new("git")
+            .args(["log", "--format=%H%x00%P%x00%B%x1e", range, "--"])
+            .output()
+            .map_err(|e| anyhow!(e))?;
+
+        if !output.status.success() {
+            let err = str::from_utf8(&output.stderr).unwrap_or_default();
+            return Err(anyhow!("{}", err.trim()));
+        }
+        let out = str::from_utf8(&output.stdout).map_err(|e| anyhow!(e))?;
+        Ok(out
+            .split('\x1e')
+            .filter_map(|record| {
+                let mut fields = record.trim_start().splitn(3, '\0');
+                Some((fields.next()?, fields.next()?, fields.next()?))
+            })
+            .map(|(sha, parents, message)| Commit {
+                sha: sha.to_string(),
+                message: message.to_string(),
+                merge: parents.split_whitespace().count() > 1,
+            })
+            .collect())
+    }
+
+    fn message(&self, rev: &str) -> Result<String, anyhow::Error> {
+        let sha = rev_parse(&format!("{}^{{commit}}", rev))?;
+        run(Command::new("git").args(["log", "-1", "--format=%B", &sha]))
+    }
+
+    fn reword(&self, rev: &str, message: &str) -> Result<String, anyhow::Error> {
+        let sha = rev_parse(&format!("{}^{{commit}}", rev))?;
+        // the message is kept as it was prompted, `#` lines included
+        if sha == rev_parse("HEAD")? {
+            return run(Command::new("git")
+                .args([
+                    "commit",
+                    "--amend",
+                    "--only",
+                    "--cleanup=verbatim",
+                    "-m",
+                    message,
+                ])
+                .env(RUNNING_ENV, "1"));
+        }
+        let parents = run(Command::new("git").args(["rev-list", "--parents", "-n", "1", &sha]))?;
+        if parents.split_whitespace().count() > 2 {
+            return Err(anyhow!("Could not reword {}: it is a merge commit", rev));
+        }
+
+        // reword the commit with a non-interactive rebase: the sequence editor marks the commit
+        // for reword, and the editor replaces its message with the new one, both given their
+        // arguments through the environment
+        let message_file = std::env::current_dir()?.join(self.git_path("GIT_BOTTLE_REWORD_MSG")?);
+        std::fs::write(&message_file, message).map_err(|e| anyhow!(e))?;
+        let base = match rev_parse(&format!("{}^", sha)) {
+            Ok(parent) => parent,
+            Err(_) => "--root".to_string(),
+        };
+        let output = run(Command::new("git")
+            .args([
+                "-c",
+                "commit.cleanup=verbatim",
+                "rebase",
+                "--interactive",
+                "--autostash",
+                "--rebase-merges",
+                &base,
+            ])
+            .env(
+                "GIT_SEQUENCE_EDITOR",
+                r#"f() { sed -e "$GIT_BOTTLE_SED" "$1" > "$1.tmp" && mv "$1.tmp" "$1"; }; f"#,
+            )
+            .env(
+                "GIT_BOTTLE_SED",
+                format!("s/^pick \\({}[0-9a-f]*\\) /reword \\1 /", &sha[..7]),
+            )
+            .env("GIT_EDITOR", r#"cp "$GIT_BOTTLE_MESSAGE""#)
+            .env("GIT_BOTTLE_MESSAGE", &message_file)
+            .env(RUNNING_ENV, "1"));
+        let _ = std::fs::remove_file(&message_file);
+        output
+    }
+}
+
+fn rev_parse(rev: &str) -> Result<String, anyhow::Error> {
+    run(Command::new("git").args(["rev-parse", "--verify", "--quiet", rev]))
+        .map(|out| out.trim().to_string())
+        .map_err(|_| anyhow!("Unknown revision {}", rev))
+}
+
+fn run(command: &mut Command) -> Result<String, anyhow::Error> {
+    let output = command.output().map_err(|e| anyhow!(e))?;
+
+    if output.status.success() {
+        let out = str::from_utf8(&output.stdout).unwrap_or_default();
+        Ok(out.to_string())
+    } else {
+        let out = str::from_utf8(&output.stdout).unwrap_or_default();
+        let err = str::from_utf8(&output.stderr).unwrap_or_default();
+        Err(anyhow!("{}\n{}", out, err))
     }
 }
@@ -0,0 +1,230 @@
+use crate::git::{Git, RUNNING_ENV};
+use crate::prompt::Prompt;
+use crate::run;
+use crate::structure::CommitStructure;
+use crate::trailers::{self, SCISSORS};
+use anyhow::anyhow;
+use inquire::InquireError;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MARKER: &str = "# Installed by git-bottle";
+
+pub fn prepare_commit_msg(
+    prompt: &impl Prompt,
+    git: &impl Git,
+    commit_structure: CommitStructure,
+    existing: &[(String, String)],
+    file: &Path,
+    source: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    if env::var_os(RUNNING_ENV).is_some() || !prompts_for(source) {
+        return Ok(());
+    }
+
+    let config = trailers::Config::read(git)?;
+    let content = fs::read_to_string(file)
+        .map_err(|e| anyhow!("Could not read file {}: {}", file.display(), e))?;
+    // the trailers already in the message, e.g. from `git commit --trailer`, are pre-selected
+    let (_, mut in_message) = trailers::split_block(&split_content(&content).0, &config);
+    for trailer in existing {
+        if !in_message.contains(trailer) {
+            in_message.push(trailer.clone());
+        }
+    }
+
+    let trailers = match run::trailers(
+        prompt,
+        git,
+        commit_structure,
+        &in_message,
+        &run::Given::default(),
+    ) {
+        Ok(trailers) => trailers,
+        Err(e) if matches!(e.downcast_ref(), Some(InquireError::NotTTY)) => {
+            eprintln!("git-bottle: no terminal available, skipping trailers");
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+    if trailers.is_empty() && in_message.is_empty() {
+        return Ok(());
+    }
+
+    fs::write(file, append_trailers(&content, &trailers, &config))
+        .map_err(|e| anyhow!("Could not write file {}: {}", file.display(), e))
+}
+
+/// Merge, squash and reused (`-c`, `-C`, `--amend`) messages are not written by the user.
+fn prompts_for(source: Option<&str>) -> bool {
+    matches!(source, None | Some("message") | Some("template"))
+}
+
+/// Splits the content of a message file into the message and the comments, with the diff of a
+/// verbose commit after the scissors line.
+fn split_content(content: &str) -> (String, String, &str) {
+    let (content, scissors) = match content.find(SCISSORS) {
+        Some(index) => content.split_at(index),
+        None => (content, ""),
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let message_end = lines
+        .iter()
+        .rposition(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        .map_or(0, |i| i + 1);
+    let message = lines[..message_end].join("\n");
+    let comments = lines[message_end..]
+        .iter()
+        .skip_while(|l| l.trim().is_empty())
+        .map(|l| format!("{}\n", l))
+        .collect::<String>();
+    (message, comments, scissors)
+}
+
+/// Replaces the trailers of the message, before the comments and the diff of a verbose commit.
+fn append_trailers(
+    content: &str,
+    trailers: &[(String, String)],
+    config: &trailers::Config,
+) -> String {
+    let (message, comments, scissors) = split_content(content);
+    let (message, _) = trailers::split_block(&message, config);
+
+    let mut result = format!("{}\n", trailers::interpret(&message, trailers, config));
+    if !comments.is_empty() {
+        result.push('\n');
+        result.push_str(&comments);
+    }
+    result.push_str(scissors);
+    result
+}
+
+pub fn install(git: &impl Git, hook: &str, command: &str) -> Result<PathBuf, anyhow::Error> {
+    let path = git.git_path(&format!("hooks/{}", hook))?;
+    if path.exists() && !is_installed(&path) {
+        return Err(anyhow!(
+            "A {} hook already exists in {}, add `git-bottle {} \"$@\"` to it manually",
+            hook,
+            path.display(),
+            command
+        ));
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| anyhow!("Could not create directory {}: {}", dir.display(), e))?;
+    }
+    fs::write(&path, script(command))
+        .map_err(|e| anyhow!("Could not write file {}: {}", path.display(), e))?;
+    set_executable(&path)?;
+    Ok(path)
+}
+
+pub fn uninstall(git: &impl Git, hook: &str) -> Result<Option<PathBuf>, anyhow::Error> {
+    let path = git.git_path(&format!("hooks/{}", hook))?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    if !is_installed(&path) {
+        return Err(anyhow!(
+            "The {} hook in {} was not installed by git-bottle, leaving it untouched",
+            hook,
+            path.display()
+        ));
+    }
+    fs::remove_file(&path)
+        .map_err(|e| anyhow!("Could not remove file {}: {}", path.display(), e))?;
+    Ok(Some(path))
+}
+
+fn is_installed(path: &Path) -> bool {
+    fs::read_to_string(path)
+        .map(|content| content.contains(MARKER))
+        .unwrap_or(false)
+}
+
+fn script(command: &str) -> String {
+    format!(
+        "#!/bin/sh\n{}\nexec git-bottle {} \"$@\"\n",
+        MARKER, command
+    )
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> Result<(), anyhow::Error> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .map_err(|e| anyhow!("Could not make {} executable: {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> Result<(), anyhow::Error> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::hook::{append_trailers, prompts_for};
+    use crate::trailers::Config;
+
+    fn issue() -> Vec<(String, String)> {
+        vec![("Issue".to_string(), "#42".to_string())]
+    }
+
+    #[test]
+    fn test_prompts_only_for_messages_written_by_the_user() {
+        assert!(prompts_for(None));
+        assert!(prompts_for(Some("message")));
+        assert!(prompts_for(Some("template")));
+        assert!(!prompts_for(Some("merge")));
+        assert!(!prompts_for(Some("squash")));
+        assert!(!prompts_for(Some("commit")));
+    }
+
+    #[test]
+    fn test_append_trailers_to_message() {
+        assert_eq!(
+            append_trailers("First commit\n", &issue(), &Config::default()),
+            "First commit\n\nIssue: #42\n"
+        );
+    }
+
+    #[test]
+    fn test_append_trailers_before_comments() {
+        let content =
+            "\n# Please enter the commit message for your changes.\n#\n# On branch main\n";
+
+        assert_eq!(
+            append_trailers(content, &issue(), &Config::default()),
+            "\n\nIssue: #42\n\n# Please enter the commit message for your changes.\n#\n# On branch main\n"
+        );
+    }
+
+    #[test]
+    fn test_append_trailers_replaces_the_existing_ones() {
+        let trailers = vec![
+            ("Issue".to_string(), "#42".to_string()),
+            ("Refs".to_string(), "#1".to_string()),
+        ];
+
+        assert_eq!(
+            append_trailers(
+                "First commit\n\nIssue: #42\n# Please enter the commit message\n",
+                &trailers,
+                &Config::default()
+            ),
+            "First commit\n\nIssue: #42\nRefs: #1\n\n# Please enter the commit message\n"
+        );
+    }
+
+    #[test]
+    fn test_append_trailers_before_scissors() {
+        let content = "First commit\n\n# ------------------------ >8 ------------------------\ndiff --git a/README.md b/README.md\n";
+
+        assert_eq!(
+            append_trailers(content, &issue(), &Config::default()),
+            "First commit\n\nIssue: #42\n# ------------------------ >8 ------------------------\ndiff --git a/README.md b/README.md\n"
+        );
+    }
+}
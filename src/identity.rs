@@ -0,0 +1,180 @@
+use crate::git::Git;
+use std::collections::BTreeMap;
+
+const GITHUB_NOREPLY: &str = "@users.noreply.github.com";
+
+/// Splits a `Name <email>` identity into its name and email.
+pub fn parse(value: &str) -> Option<(&str, &str)> {
+    let (name, email) = value.trim().strip_suffix('>')?.rsplit_once('<')?;
+    let (name, email) = (name.trim(), email.trim());
+    match name.is_empty() || email.is_empty() {
+        true => None,
+        false => Some((name, email)),
+    }
+}
+
+/// Maps every identity among the values to the canonical spelling of its human: the one declared
+/// in the configuration, else the one of the `.mailmap`, else the first one found which is not a
+/// GitHub noreply email. Values which are not identities are left out.
+pub fn canonical(
+    git: &impl Git,
+    values: &[String],
+    equivalences: &BTreeMap<String, Vec<String>>,
+) -> Result<BTreeMap<String, String>, anyhow::Error> {
+    let mut originals: Vec<String> = vec![];
+    for value in values {
+        if parse(value).is_some() && !originals.contains(value) {
+            originals.push(value.clone());
+        }
+    }
+    if originals.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    // e.g. a git without `check-mailmap` or a malformed entry, which must not prevent committing
+    let mailmapped = git.check_mailmap(&originals).unwrap_or_else(|e| {
+        eprintln!(
+            "warning: could not read the mailmap, identities are not merged with it: {}",
+            e
+        );
+        originals.clone()
+    });
+    let mapped: Vec<String> = mailmapped
+        .into_iter()
+        .map(|identity| declared(equivalences, &identity).unwrap_or(identity))
+        .collect();
+
+    // group the identities of the same human
+    let mut groups: Vec<usize> = (0..mapped.len()).collect();
+    for i in 0..mapped.len() {
+        for j in 0..i {
+            if groups[i] != groups[j] && same(&mapped[i], &mapped[j]) {
+                let (from, to) = (groups[i], groups[j]);
+                groups
+                    .iter_mut()
+                    .filter(|g| **g == from)
+                    .for_each(|g| *g = to);
+            }
+        }
+    }
+
+    let mut canonical = BTreeMap::new();
+    for (original, group) in originals.iter().zip(&groups) {
+        let members: Vec<&String> = mapped
+            .iter()
+            .zip(&groups)
+            .filter(|(_, g)| *g == group)
+            .map(|(identity, _)| identity)
+            .collect();
+        let spelling = members
+            .iter()
+            .find(|m| equivalences.contains_key(m.as_str()))
+            .or_else(|| members.iter().find(|m| !is_noreply(m)))
+            .unwrap_or(&members[0]);
+        canonical.insert(original.clone(), spelling.to_string());
+    }
+    Ok(canonical)
+}
+
+/// The canonical identity declared for an identity, matching an alias by identity or by email.
+fn declared(equivalences: &BTreeMap<String, Vec<String>>, identity: &str) -> Option<String> {
+    let (name, email) = parse(identity)?;
+    equivalences
+        .iter()
+        .find(|(canonical, aliases)| {
+            std::iter::once(*canonical)
+                .chain(aliases.iter())
+                .any(|alias| match parse(alias) {
+                    Some((n, e)) => same_name(n, name) && e.eq_ignore_ascii_case(email),
+                    None => alias.trim().eq_ignore_ascii_case(email),
+                })
+        })
+        .map(|(canonical, _)| canonical.clone())
+}
+
+/// Whether two identities are the same human: their emails are equal, or their names are equal
+/// and one of them is a GitHub noreply email.
+fn same(a: &str, b: &str) -> bool {
+    match (parse(a), parse(b)) {
+        (Some((name_a, email_a)), Some((name_b, email_b))) => {
+            email_a.eq_ignore_ascii_case(email_b)
+                || (same_name(name_a, name_b) && (is_noreply(a) || is_noreply(b)))
+        }
+        _ => false,
+    }
+}
+
+fn same_name(a: &str, b: &str) -> bool {
+    let words =
+        |name: &str| -> Vec<String> { name.split_whitespace().map(|w| w.to_lowercase()).collect() };
+    words(a) == words(b)
+}
+
+fn is_noreply(identity: &str) -> bool {
+    parse(identity).is_some_and(|(_, email)| email.to_lowercase().ends_with(GITHUB_NOREPLY))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::git::MockGit;
+    use crate::identity::canonical;
+    use anyhow::anyhow;
+    use std::collections::BTreeMap;
+
+    fn identities(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_canonical() {
+        let mut git: MockGit = MockGit::new();
+        git.expect_check_mailmap().returning(|contacts| {
+            Ok(contacts
+                .iter()
+                .map(|c| c.replace("<jd@old.example.org>", "<jane@corp.com>"))
+                .collect())
+        });
+        let values = identities(&[
+            "jane doe <Jane@Corp.com>",
+            "Jane Doe <12345+jane@users.noreply.github.com>",
+            "Jane Doe <jane@corp.com>",
+            "J. Doe <jd@old.example.org>",
+            "Joe Shmoe <joe@corp.com>",
+            "Joe Shmoe <joe@home.example.org>",
+            "#42",
+        ]);
+        let equivalences = BTreeMap::from([(
+            "Joe Shmoe <joe@corp.com>".to_string(),
+            vec!["joe@home.example.org".to_string()],
+        )]);
+
+        let canonical = canonical(&git, &values, &equivalences).unwrap();
+
+        assert_eq!(
+            canonical.values().collect::<Vec<&String>>(),
+            vec![
+                "jane doe <Jane@Corp.com>",
+                "jane doe <Jane@Corp.com>",
+                "jane doe <Jane@Corp.com>",
+                "Joe Shmoe <joe@corp.com>",
+                "Joe Shmoe <joe@corp.com>",
+                "jane doe <Jane@Corp.com>",
+            ]
+        );
+        assert_eq!(canonical.get("#42"), None);
+    }
+
+    #[test]
+    fn test_canonical_without_mailmap() {
+        let mut git: MockGit = MockGit::new();
+        git.expect_check_mailmap()
+            .returning(|_| Err(anyhow!("git: 'check-mailmap' is not a git command")));
+        let values = identities(&["Jane Doe <jane@corp.com>", "jane doe <Jane@Corp.com>"]);
+
+        let canonical = canonical(&git, &values, &BTreeMap::new()).unwrap();
+
+        assert_eq!(
+            canonical.values().collect::<Vec<&String>>(),
+            vec!["Jane Doe <jane@corp.com>", "Jane Doe <jane@corp.com>"]
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use crate::config;
+use crate::migrate::CURRENT_VERSION;
+use crate::prompt::Prompt;
+use crate::structure::{
+    CommitStructure, MultiSelectTrailer, Order, SelectTrailer, TextTrailer, Trailer, Validate,
+    Values, ValuesFromGitLog, ValuesFromOptions,
+};
+use anyhow::anyhow;
+
+const CO_AUTHORS_FROM_GIT_LOG: &str = "from the git log";
+const CO_AUTHORS_FROM_LIST: &str = "from a list of identities";
+const NONE: &str = "none";
+
+/// Asks for the trailers of a new configuration, starting from the presets of the examples.
+pub fn init(prompt: &impl Prompt) -> Result<CommitStructure, anyhow::Error> {
+    let mut trailers = vec![];
+
+    let co_authors = prompt.select(
+        "Co-authored-by:",
+        vec![
+            CO_AUTHORS_FROM_GIT_LOG.to_string(),
+            CO_AUTHORS_FROM_LIST.to_string(),
+            NONE.to_string(),
+        ],
+        false,
+        Some(0),
+        &Validate::default(),
+    )?;
+    match co_authors.as_str() {
+        CO_AUTHORS_FROM_GIT_LOG => trailers.extend(config::default_config().trailers),
+        CO_AUTHORS_FROM_LIST => {
+            let options = prompt.multi_text(
+                "Co-author (Name <email>), empty to finish:",
+                &Validate {
+                    identity: true,
+                    ..Default::default()
+                },
+            )?;
+            trailers.push(multi_select("Co-authored-by", from_options(options)));
+        }
+        _ => {}
+    }
+
+    if prompt.confirm(
+        "Add an Issue trailer selected from the last 10 commits?",
+        false,
+    )? {
+        trailers.push(Trailer::Select(SelectTrailer {
+            name: "Issue".to_string(),
+            when: None,
+            validate: Validate::default(),
+            default: None,
+            values: from_git_log("Issue", Some(10)),
+        }));
+    }
+
+    while prompt.confirm("Add another trailer?", false)? {
+        let trailer = custom_trailer(prompt)?;
+        trailers.retain(|t| !t.name().eq_ignore_ascii_case(trailer.name()));
+        trailers.push(trailer);
+    }
+
+    Ok(CommitStructure {
+        version: Some(CURRENT_VERSION),
+        trailers,
+        ..Default::default()
+    })
+}
+
+/// The rules of a trailer name: letters, digits and dashes.
+fn trailer_name_rules() -> Validate {
+    Validate {
+        required: true,
+        pattern: Some("^[A-Za-z0-9-]+$".to_string()),
+        ..Default::default()
+    }
+}
+
+fn custom_trailer(prompt: &impl Prompt) -> Result<Trailer, anyhow::Error> {
+    // an invalid name is prompted for again
+    let name = prompt.text("Trailer name:", "", &trailer_name_rules())?;
+
+    let kind = prompt.select(
+        "Type:",
+        vec![
+            "text".to_string(),
+            "select".to_string(),
+            "multi_select".to_string(),
+        ],
+        false,
+        None,
+        &Validate::default(),
+    )?;
+    if kind == "text" {
+        return Ok(Trailer::Text(TextTrailer {
+            name,
+            when: None,
+            validate: Validate::default(),
+            default: None,
+        }));
+    }
+
+    let source = prompt.select(
+        "Values:",
+        vec!["from_options".to_string(), "from_git_log".to_string()],
+        false,
+        None,
+        &Validate::default(),
+    )?;
+    let values = match source.as_str() {
+        "from_options" => {
+            from_options(prompt.multi_text("Option, empty to finish:", &Validate::default())?)
+        }
+        _ => from_git_log(&name, None),
+    };
+    Ok(match kind.as_str() {
+        "select" => Trailer::Select(SelectTrailer {
+            name,
+            when: None,
+            validate: Validate::default(),
+            default: None,
+            values,
+        }),
+        _ => multi_select(&name, values),
+    })
+}
+
+fn multi_select(name: &str, values: Values) -> Trailer {
+    Trailer::MultiSelect(MultiSelectTrailer {
+        name: name.to_string(),
+        when: None,
+        validate: Validate::default(),
+        default: None,
+        values,
+    })
+}
+
+fn from_options(options: Vec<String>) -> Values {
+    Values::FromOptions(ValuesFromOptions { options })
+}
+
+/// The values of the trailer in the git log.
+fn from_git_log(name: &str, max_count: Option<i32>) -> Values {
+    Values::FromGitLog(ValuesFromGitLog {
+        max_count,
+        format_strings: vec![format!("%(trailers:key={},valueonly=true)", name)],
+        order: Order::Alpha,
+        half_life: None,
+        exclude_self: true,
+    })
+}
+
+pub fn to_yaml(commit_structure: &CommitStructure) -> Result<String, anyhow::Error> {
+    serde_yaml::to_string(commit_structure).map_err(|e| anyhow!(e))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::check::check_value;
+    use crate::init::{init, to_yaml, trailer_name_rules};
+    use crate::prompt::MockPrompt;
+    use mockall::predicate::{always, eq};
+
+    #[test]
+    fn test_init() {
+        let mut prompt: MockPrompt = MockPrompt::new();
+
+        prompt
+            .expect_select()
+            .with(
+                eq("Co-authored-by:"),
+                always(),
+                eq(false),
+                eq(Some(0)),
+                always(),
+            )
+            .returning(|_, _, _, _, _| Ok("from a list of identities".to_string()));
+        prompt
+            .expect_multi_text()
+            .with(eq("Co-author (Name <email>), empty to finish:"), always())
+            .returning(|_, _| Ok(vec!["Jane Doe <jane.doe@example.org>".to_string()]));
+        prompt
+            .expect_confirm()
+            .with(
+                eq("Add an Issue trailer selected from the last 10 commits?"),
+                eq(false),
+            )
+            .returning(|_, _| Ok(true));
+        let mut more = vec![false, true];
+        prompt
+            .expect_confirm()
+            .with(eq("Add another trailer?"), eq(false))
+            .returning(move |_, _| Ok(more.pop().unwrap()));
+        prompt
+            .expect_text()
+            .with(eq("Trailer name:"), eq(""), eq(trailer_name_rules()))
+            .returning(|_, _, _| Ok("Refs".to_string()));
+        prompt
+            .expect_select()
+            .with(eq("Type:"), always(), eq(false), eq(None), always())
+            .returning(|_, _, _, _, _| Ok("text".to_string()));
+
+        assert_eq!(
+            to_yaml(&init(&prompt).unwrap()).unwrap(),
+            "version: 1
+trailers:
+- type: multi_select
+  name: Co-authored-by
+  values:
+    type: from_options
+    options:
+    - Jane Doe <jane.doe@example.org>
+- type: select
+  name: Issue
+  values:
+    type: from_git_log
+    max_count: 10
+    format_strings:
+    - '%(trailers:key=Issue,valueonly=true)'
+- type: text
+  name: Refs
+"
+        );
+    }
+
+    #[test]
+    fn test_trailer_name_rules() {
+        let valid = |name: &str| {
+            check_value("Trailer name", &trailer_name_rules(), name)
+                .unwrap()
+                .is_empty()
+        };
+
+        assert!(valid("Reviewed-by"));
+        assert!(!valid("Reviewed by"));
+        assert!(!valid("Issue:"));
+    }
+}
@@ -0,0 +1,228 @@
+use crate::check::{check, Violation};
+use crate::git::Git;
+use crate::structure::CommitStructure;
+use crate::trailers;
+use crate::when;
+use anyhow::anyhow;
+use serde_json::json;
+
+pub struct Report {
+    pub sha: String,
+    pub subject: String,
+    pub violations: Vec<Violation>,
+}
+
+/// Checks the trailers of every commit in the range against the configuration, but the merge
+/// commits, which are not written by the user. The conditions on the branch or the staged files do
+/// not apply to past commits, these trailers are optional.
+pub fn lint<G: Git>(
+    git: &G,
+    commit_structure: &CommitStructure,
+    config: &trailers::Config,
+    range: &str,
+) -> Result<Vec<Report>, anyhow::Error> {
+    let mut context = when::Context::<G>::answers_only();
+    git.commits(range)?
+        .into_iter()
+        .filter(|commit| !commit.merge)
+        .map(|commit| {
+            let violations = check(
+                commit_structure,
+                &trailers::parse(&commit.message, config),
+                &mut context,
+            )?;
+            Ok(Report {
+                sha: commit.sha,
+                subject: commit
+                    .message
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                violations,
+            })
+        })
+        .collect()
+}
+
+pub fn is_valid(reports: &[Report]) -> bool {
+    reports.iter().all(|r| r.violations.is_empty())
+}
+
+pub fn to_human(reports: &[Report]) -> String {
+    let invalid: Vec<&Report> = reports
+        .iter()
+        .filter(|r| !r.violations.is_empty())
+        .collect();
+
+    let mut out = String::new();
+    for report in &invalid {
+        out.push_str(&format!("{} {}\n", short(&report.sha), report.subject));
+        for violation in &report.violations {
+            out.push_str(&format!("  error: {}\n", violation));
+        }
+        out.push('\n');
+    }
+    if invalid.is_empty() {
+        out.push_str(&format!(
+            "{} commits checked, all trailers are valid",
+            reports.len()
+        ));
+    } else {
+        out.push_str(&format!(
+            "{} of {} commits have invalid trailers",
+            invalid.len(),
+            reports.len()
+        ));
+    }
+    out
+}
+
+pub fn to_json(reports: &[Report]) -> Result<String, anyhow::Error> {
+    let commits: Vec<serde_json::Value> = reports
+        .iter()
+        .map(|r| {
+            json!({
+                "sha": r.sha,
+                "subject": r.subject,
+                "violations": r.violations.iter().map(|v| v.to_string()).collect::<Vec<String>>(),
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&json!({
+        "valid": is_valid(reports),
+        "commits": commits,
+    }))
+    .map_err(|e| anyhow!(e))
+}
+
+pub fn to_junit(reports: &[Report]) -> String {
+    let failures = reports.iter().filter(|r| !r.violations.is_empty()).count();
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<testsuite name=\"git-bottle lint\" tests=\"{}\" failures=\"{}\">\n",
+        reports.len(),
+        failures
+    ));
+    for report in reports {
+        let name = escape(&format!("{} {}", short(&report.sha), report.subject));
+        if report.violations.is_empty() {
+            out.push_str(&format!(
+                "  <testcase classname=\"trailers\" name=\"{}\"/>\n",
+                name
+            ));
+            continue;
+        }
+        let violations: Vec<String> = report.violations.iter().map(|v| v.to_string()).collect();
+        out.push_str(&format!(
+            "  <testcase classname=\"trailers\" name=\"{}\">\n",
+            name
+        ));
+        out.push_str(&format!(
+            "    <failure message=\"{}\">{}</failure>\n",
+            escape(&violations[0]),
+            escape(&violations.join("\n"))
+        ));
+        out.push_str("  </testcase>\n");
+    }
+    out.push_str("</testsuite>");
+    out
+}
+
+fn short(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::git::{Commit, MockGit};
+    use crate::lint::{lint, to_human, to_junit};
+    use crate::structure::{CommitStructure, TextTrailer, Trailer, Validate};
+    use crate::trailers::Config;
+    use mockall::predicate::eq;
+
+    fn reports() -> Vec<crate::lint::Report> {
+        let commit_structure = CommitStructure {
+            trailers: vec![Trailer::Text(TextTrailer {
+                name: "Issue".to_string(),
+                when: None,
+                validate: Validate {
+                    required: true,
+                    ..Default::default()
+                },
+                default: None,
+            })],
+            ..Default::default()
+        };
+
+        let mut git: MockGit = MockGit::new();
+        git.expect_commits()
+            .with(eq("origin/main..HEAD"))
+            .returning(|_| {
+                Ok(vec![
+                    Commit {
+                        sha: "0123456789abcdef".to_string(),
+                        message: "Second commit <3\n\nIssue: #42\n".to_string(),
+                        merge: false,
+                    },
+                    Commit {
+                        sha: "0011223344556677".to_string(),
+                        message: "Merge branch 'main' into feature\n".to_string(),
+                        merge: true,
+                    },
+                    Commit {
+                        sha: "fedcba9876543210".to_string(),
+                        message: "First commit\n\nCo-authored-by: jane\n".to_string(),
+                        merge: false,
+                    },
+                ])
+            });
+
+        lint(
+            &git,
+            &commit_structure,
+            &Config::default(),
+            "origin/main..HEAD",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_merge_commits_are_skipped() {
+        let shas: Vec<String> = reports().into_iter().map(|r| r.sha).collect();
+
+        assert_eq!(shas, vec!["0123456789abcdef", "fedcba9876543210"]);
+    }
+
+    #[test]
+    fn test_human_output() {
+        assert_eq!(
+            to_human(&reports()),
+            "fedcba9 First commit\n  error: missing required trailer \"Issue\"\n  error: \"Co-authored-by: jane\" is not in the format \"Name <email>\"\n\n1 of 2 commits have invalid trailers"
+        );
+    }
+
+    #[test]
+    fn test_junit_output() {
+        assert_eq!(
+            to_junit(&reports()),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<testsuite name=\"git-bottle lint\" tests=\"2\" failures=\"1\">
+  <testcase classname=\"trailers\" name=\"0123456 Second commit &lt;3\"/>
+  <testcase classname=\"trailers\" name=\"fedcba9 First commit\">
+    <failure message=\"missing required trailer &quot;Issue&quot;\">missing required trailer &quot;Issue&quot;
+&quot;Co-authored-by: jane&quot; is not in the format &quot;Name &lt;email&gt;&quot;</failure>
+  </testcase>
+</testsuite>"
+        );
+    }
+}
@@ -1,22 +1,110 @@
 extern crate serde_yaml;
 
+mod authors;
+mod check;
+mod cli;
 mod config;
+mod conventional;
 mod git;
+mod hook;
+mod identity;
+mod init;
+mod lint;
+mod message;
+mod migrate;
 mod prompt;
 mod run;
+mod session;
 mod structure;
+mod trailers;
+mod when;
 
+use crate::check::CO_AUTHORED_BY;
+use crate::cli::{
+    AuthorsArgs, AuthorsCommand, Cli, Command, CommitArgs, ConfigArgs, ConfigCommand, GivenArgs,
+    HookArgs, HookCommand, LintFormat, MessageArgs, SessionArgs, SessionCommand,
+};
 use crate::git::{Git, GitCommand};
-use crate::prompt::PromptInquire;
+use crate::prompt::{Prompt, PromptInquire};
+use crate::run::{Draft, Given};
+use crate::structure::CommitStructure;
+use anyhow::anyhow;
+use clap::Parser;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 
 fn main() -> Result<(), anyhow::Error> {
-    let prompt = PromptInquire {};
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Commit(args)) => commit(&args),
+        Some(Command::Amend(args)) => commit(&CommitArgs {
+            amend: true,
+            ..args
+        }),
+        Some(Command::Reword { rev }) => reword(&rev),
+        Some(Command::Message(args)) => message(&args),
+        Some(Command::Hook(args)) => hook(&args),
+        Some(Command::Session(args)) => session(&args),
+        Some(Command::Authors(args)) => authors(&args),
+        Some(Command::Check { file }) => check(&file),
+        Some(Command::Init) => init(),
+        Some(Command::Config(args)) => config(&args),
+        Some(Command::Lint { range, format }) => lint(&range, format),
+        None => commit(&cli.commit),
+    }
+}
+
+fn commit(args: &CommitArgs) -> Result<(), anyhow::Error> {
+    let git = GitCommand {};
+
+    let structure = config::get(&git)?;
+    let given = given(&structure, &args.given, args.given_message())?;
+
+    let draft = match args.amend {
+        true => Draft::parse(&git.message("HEAD")?, &trailers::Config::read(&git)?),
+        false => new_draft(&git)?,
+    };
+    let message = run::run(&PromptInquire {}, &git, structure, &draft, &given)?;
+    if args.dry_run {
+        println!("{}", message);
+        return Ok(());
+    }
+    match git.commit(&message, &args.to_git_args()) {
+        Ok(out) => println!("{}", out),
+        Err(err) => {
+            println!("{}", err);
+            exit(1)
+        }
+    }
+    Ok(())
+}
+
+/// The answers given on the command line, expanding the handles of the co-authors.
+fn given(
+    structure: &CommitStructure,
+    args: &GivenArgs,
+    message: Option<&String>,
+) -> Result<Given, anyhow::Error> {
+    let mut trailers = args.trailers.clone();
+    for handle in &args.with {
+        let identity = authors::expand(&structure.authors, handle)
+            .ok_or_else(|| anyhow!("Unknown author {}", handle))?;
+        trailers.push((CO_AUTHORED_BY.to_string(), identity.clone()));
+    }
+    Ok(Given {
+        message: message.cloned(),
+        trailers,
+        no_interactive: args.no_interactive,
+    })
+}
+
+fn reword(rev: &str) -> Result<(), anyhow::Error> {
     let git = GitCommand {};
 
-    let structure = config::get()?;
-    let message = run::run(&prompt, &git, structure)?;
-    match git.commit(&message) {
+    let draft = Draft::parse(&git.message(rev)?, &trailers::Config::read(&git)?);
+    let message = compose(&git, &draft)?;
+    match git.reword(rev, &message) {
         Ok(out) => println!("{}", out),
         Err(err) => {
             println!("{}", err);
@@ -25,3 +113,320 @@ fn main() -> Result<(), anyhow::Error> {
     }
     Ok(())
 }
+
+fn message(args: &MessageArgs) -> Result<(), anyhow::Error> {
+    let git = GitCommand {};
+
+    let structure = config::get(&git)?;
+    let given = given(&structure, &args.given, args.given.message.as_ref())?;
+    let message = run::run(
+        &PromptInquire {},
+        &git,
+        structure,
+        &new_draft(&git)?,
+        &given,
+    )?;
+    match &args.output {
+        Some(path) => std::fs::write(path, format!("{}\n", message))
+            .map_err(|e| anyhow!("Could not write file {}: {}", path.display(), e)),
+        None => {
+            println!("{}", message);
+            Ok(())
+        }
+    }
+}
+
+fn hook(args: &HookArgs) -> Result<(), anyhow::Error> {
+    let git = GitCommand {};
+
+    match &args.command {
+        HookCommand::PrepareCommitMsg { file, source, .. } => {
+            let prompt = PromptInquire {};
+            let structure = config::get(&git)?;
+            let draft = new_draft(&git)?;
+            hook::prepare_commit_msg(
+                &prompt,
+                &git,
+                structure,
+                &draft.trailers,
+                file,
+                source.as_deref(),
+            )
+        }
+        HookCommand::Install { hook } => {
+            let path = hook::install(&git, hook.name(), hook.command())?;
+            println!("Installed {} hook in {}", hook.name(), path.display());
+            Ok(())
+        }
+        HookCommand::Uninstall { hook } => {
+            match hook::uninstall(&git, hook.name())? {
+                Some(path) => println!("Removed {} hook from {}", hook.name(), path.display()),
+                None => println!("No {} hook installed", hook.name()),
+            }
+            Ok(())
+        }
+    }
+}
+
+fn session(args: &SessionArgs) -> Result<(), anyhow::Error> {
+    let git = GitCommand {};
+
+    let now = session::now();
+    let current = session::load(&git, now)?;
+    match &args.command {
+        SessionCommand::Start { expires_in } => {
+            let prompt = PromptInquire {};
+            let structure = config::get(&git)?;
+            let expires_at = now
+                .checked_add(session::parse_duration(expires_in)?)
+                .ok_or_else(|| anyhow!("Invalid duration {}, it is too long", expires_in))?;
+            let session = session::start(&prompt, &git, structure, current, expires_at)?;
+            session::save(&git, &session)?;
+            print_session(&session, now);
+        }
+        SessionCommand::Show => match current {
+            Some(session) => print_session(&session, now),
+            None => println!("No active session"),
+        },
+        SessionCommand::Add { who, trailer } => {
+            let mut session = current.ok_or_else(no_session)?;
+            let structure = config::get(&git)?;
+            let who = authors::expand(&structure.authors, who).unwrap_or(who);
+            // the rules of the trailer when it is configured
+            let validate = structure
+                .trailers
+                .iter()
+                .find(|t| t.name().eq_ignore_ascii_case(trailer))
+                .map(|t| t.validate().clone())
+                .unwrap_or_default();
+            session.add(trailer, who, &validate)?;
+            session::save(&git, &session)?;
+            print_session(&session, now);
+        }
+        SessionCommand::Remove { who, trailer } => {
+            let mut session = current.ok_or_else(no_session)?;
+            let authors = config::get(&git)?.authors;
+            let who = authors::expand(&authors, who).unwrap_or(who);
+            if !session.remove(trailer, who) {
+                return Err(anyhow!("\"{}: {}\" is not in the session", trailer, who));
+            }
+            session::save(&git, &session)?;
+            print_session(&session, now);
+        }
+        SessionCommand::End => match session::end(&git)? {
+            true => println!("Session ended"),
+            false => println!("No active session"),
+        },
+    }
+    Ok(())
+}
+
+fn authors(args: &AuthorsArgs) -> Result<(), anyhow::Error> {
+    match &args.command {
+        AuthorsCommand::Import { files, output } => {
+            let output = match output {
+                Some(output) => output.clone(),
+                None => authors::user_roster_path()
+                    .ok_or_else(|| anyhow!("Could not find the home directory"))?,
+            };
+            let files = match files.is_empty() {
+                true => default_import_files(),
+                false => files.clone(),
+            };
+
+            let mut roster = authors::load(&output)?;
+            for file in files {
+                let imported = import_authors(&file)?;
+                println!(
+                    "Imported {} authors from {}",
+                    imported.len(),
+                    file.display()
+                );
+                roster.authors.extend(imported);
+            }
+            authors::save(&output, &roster)?;
+            println!(
+                "Saved {} authors in {}",
+                roster.authors.len(),
+                output.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+/// The git-mob and git-duet files in the home directory.
+fn default_import_files() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    [".git-coauthors", ".git-authors"]
+        .iter()
+        .map(|name| home.join(name))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+fn import_authors(file: &Path) -> Result<BTreeMap<String, String>, anyhow::Error> {
+    let content = std::fs::read_to_string(file)
+        .map_err(|e| anyhow!("Could not read file {}: {}", file.display(), e))?;
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    let authors = match name.as_ref() {
+        ".git-coauthors" => authors::from_git_mob(&content),
+        ".git-authors" => authors::from_git_duet(&content),
+        _ => Err(anyhow!("expected a .git-coauthors or .git-authors file")),
+    };
+    authors.map_err(|e| anyhow!("Could not import authors from {}: {}", file.display(), e))
+}
+
+fn print_session(session: &session::Session, now: u64) {
+    for trailer in run::format_trailers(&session.trailers) {
+        println!("{}", trailer);
+    }
+    println!(
+        "Session expires in {}",
+        session::format_duration(session.expires_at.saturating_sub(now))
+    );
+}
+
+fn no_session() -> anyhow::Error {
+    anyhow!("No active session, start one with `git bottle session start`")
+}
+
+fn init() -> Result<(), anyhow::Error> {
+    let git = GitCommand {};
+    let prompt = PromptInquire {};
+
+    let path = git.top_level()?.join(".git-bottle.yml");
+    if path.exists() && !prompt.confirm(&format!("Overwrite {}?", path.display()), false)? {
+        return Ok(());
+    }
+    let yaml = init::to_yaml(&init::init(&prompt)?)?;
+    println!("{}", yaml);
+    if prompt.confirm(&format!("Write {}?", path.display()), true)? {
+        std::fs::write(&path, yaml)
+            .map_err(|e| anyhow!("Could not write file {}: {}", path.display(), e))?;
+        println!("Written {}", path.display());
+    }
+    Ok(())
+}
+
+fn config(args: &ConfigArgs) -> Result<(), anyhow::Error> {
+    let git = GitCommand {};
+
+    match &args.command {
+        ConfigCommand::Show => {
+            let (structure, origins) = config::load(&git)?;
+            print!("{}", config::show(&structure, &origins)?);
+        }
+        ConfigCommand::Schema => println!("{}", config::schema()?),
+        ConfigCommand::Path => {
+            for path in config::paths(&git)? {
+                println!("{}", path.display());
+            }
+        }
+        ConfigCommand::Validate { file } => {
+            let files = match file {
+                Some(file) => vec![file.clone()],
+                None => config::paths(&git)?,
+            };
+            let mut valid = true;
+            for file in files {
+                let content = std::fs::read_to_string(&file)
+                    .map_err(|e| anyhow!("Could not read file {}: {}", file.display(), e))?;
+                match config::validate(&file, &content) {
+                    Ok(()) => println!("{}: valid", file.display()),
+                    Err(err) => {
+                        eprintln!("error: {}", err);
+                        valid = false;
+                    }
+                }
+            }
+            if !valid {
+                exit(1)
+            }
+        }
+        ConfigCommand::Migrate { file } => {
+            let files = match file {
+                Some(file) => vec![file.clone()],
+                None => config::paths(&git)?,
+            };
+            for file in files {
+                let content = std::fs::read_to_string(&file)
+                    .map_err(|e| anyhow!("Could not read file {}: {}", file.display(), e))?;
+                match migrate::upgrade(&file, &content)? {
+                    None => println!("{}: up to date", file.display()),
+                    Some((content, changes)) => {
+                        std::fs::write(&file, content).map_err(|e| {
+                            anyhow!("Could not write file {}: {}", file.display(), e)
+                        })?;
+                        println!(
+                            "{}: upgraded to version {}",
+                            file.display(),
+                            migrate::CURRENT_VERSION
+                        );
+                        for change in changes {
+                            println!("  {}", change);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check(file: &Path) -> Result<(), anyhow::Error> {
+    let git = GitCommand {};
+
+    let content = std::fs::read_to_string(file)
+        .map_err(|e| anyhow!("Could not read file {}: {}", file.display(), e))?;
+
+    let structure = config::get(&git)?;
+    let violations = check::check(
+        &structure,
+        &trailers::parse(&content, &trailers::Config::read(&git)?),
+        &mut when::Context::new(&git),
+    )?;
+    if !violations.is_empty() {
+        for violation in violations {
+            eprintln!("error: {}", violation);
+        }
+        exit(1)
+    }
+    Ok(())
+}
+
+fn lint(range: &str, format: LintFormat) -> Result<(), anyhow::Error> {
+    let git = GitCommand {};
+
+    let structure = config::get(&git)?;
+    let reports = lint::lint(&git, &structure, &trailers::Config::read(&git)?, range)?;
+    match format {
+        LintFormat::Human => println!("{}", lint::to_human(&reports)),
+        LintFormat::Json => println!("{}", lint::to_json(&reports)?),
+        LintFormat::Junit => println!("{}", lint::to_junit(&reports)),
+    }
+    if !lint::is_valid(&reports) {
+        exit(1)
+    }
+    Ok(())
+}
+
+/// The draft of a new commit, with the trailers of the current session.
+fn new_draft(git: &impl Git) -> Result<Draft, anyhow::Error> {
+    let trailers = session::load(git, session::now())?
+        .map(|s| s.trailers)
+        .unwrap_or_default();
+    Ok(Draft {
+        message: String::new(),
+        trailers,
+    })
+}
+
+fn compose(git: &impl Git, draft: &Draft) -> Result<String, anyhow::Error> {
+    let prompt = PromptInquire {};
+
+    let structure = config::get(git)?;
+    run::run(&prompt, git, structure, draft, &Given::default())
+}
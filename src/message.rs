@@ -0,0 +1,494 @@
+use crate::git::Git;
+use crate::prompt::Prompt;
+use crate::structure::{CommitStructure, Message, MessageStyle, Validate};
+use anyhow::anyhow;
+use regex::Regex;
+
+/// The answers to the prompts of the message.
+#[derive(Debug, Default, PartialEq)]
+pub struct Answers {
+    /// The whole subject line, e.g. `feat(api): drop the v1 endpoints`
+    pub subject: String,
+    /// The subject without its conventional prefix, e.g. `drop the v1 endpoints`
+    pub summary: String,
+    pub kind: String,
+    pub scope: String,
+    pub body: String,
+}
+
+impl Answers {
+    pub fn message(&self) -> String {
+        match self.body.trim() {
+            "" => self.subject.clone(),
+            body => format!("{}\n\n{}", self.subject, body),
+        }
+    }
+}
+
+/// The placeholders of a template which are not trailers, for a message of the style.
+fn builtin_placeholders(style: MessageStyle) -> Vec<&'static str> {
+    match style {
+        MessageStyle::Free => vec!["subject", "summary", "body", "branch", "ticket"],
+        MessageStyle::Conventional => vec![
+            "subject", "summary", "type", "scope", "body", "branch", "ticket",
+        ],
+    }
+}
+
+enum Part {
+    Text(String),
+    Placeholder(String),
+}
+
+/// Splits a template into its text and its `{placeholder}`s, `{{` and `}}` being literal braces.
+fn parse(template: &str) -> Result<Vec<Part>, anyhow::Error> {
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some('{') | None => {
+                            return Err(anyhow!(
+                                "Unclosed placeholder {{{} in message.template",
+                                name
+                            ))
+                        }
+                        Some(c) => name.push(c),
+                    }
+                }
+                parts.push(Part::Text(std::mem::take(&mut text)));
+                parts.push(Part::Placeholder(name.trim().to_string()));
+            }
+            '}' => {
+                return Err(anyhow!(
+                    "Unopened placeholder in message.template, use }}}} for a brace"
+                ))
+            }
+            c => text.push(c),
+        }
+    }
+    parts.push(Part::Text(text));
+    Ok(parts)
+}
+
+/// Whether the template of the message has the placeholder.
+pub fn uses(message: &Message, placeholder: &str) -> bool {
+    let Some(Ok(parts)) = message.template.as_deref().map(parse) else {
+        return false;
+    };
+    parts
+        .iter()
+        .any(|part| matches!(part, Part::Placeholder(name) if name == placeholder))
+}
+
+/// Checks that the placeholders of the template are known, i.e. built in or configured trailers.
+pub fn check_template(structure: &CommitStructure) -> Result<(), anyhow::Error> {
+    let Some(template) = &structure.message.template else {
+        return Ok(());
+    };
+    let builtin = builtin_placeholders(structure.message.style);
+    for part in parse(template)? {
+        let Part::Placeholder(name) = part else {
+            continue;
+        };
+        let known = builtin.contains(&name.as_str())
+            || structure
+                .trailers
+                .iter()
+                .any(|t| t.name().eq_ignore_ascii_case(&name));
+        if !known {
+            let mut expected: Vec<String> = builtin.iter().map(|p| p.to_string()).collect();
+            expected.extend(structure.trailers.iter().map(|t| t.name().to_string()));
+            return Err(anyhow!(
+                "Unknown placeholder {{{}}} in message.template, expected one of: {}",
+                name,
+                expected.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Renders the template of the message with the answers, the branch and the values of the
+/// trailers, returning the trailers which are not rendered in the message.
+pub fn render(
+    git: &impl Git,
+    message: &Message,
+    answers: &Answers,
+    trailers: Vec<(String, String)>,
+) -> Result<(String, Vec<(String, String)>), anyhow::Error> {
+    let Some(template) = &message.template else {
+        return Ok((answers.message(), trailers));
+    };
+    let mut branch: Option<String> = None;
+    let mut rendered = String::new();
+    let mut used: Vec<String> = vec![];
+    for part in parse(template)? {
+        let name = match part {
+            Part::Text(text) => {
+                rendered.push_str(&text);
+                continue;
+            }
+            Part::Placeholder(name) => name,
+        };
+        let value = match name.as_str() {
+            "subject" => answers.subject.clone(),
+            "summary" => answers.summary.clone(),
+            "type" => answers.kind.clone(),
+            "scope" => answers.scope.clone(),
+            "body" => answers.body.trim().to_string(),
+            "branch" | "ticket" => {
+                if branch.is_none() {
+                    branch = Some(git.current_branch()?.unwrap_or_default());
+                }
+                let branch = branch.as_deref().unwrap_or_default();
+                match name.as_str() {
+                    "branch" => branch.to_string(),
+                    _ => ticket(message, branch)?,
+                }
+            }
+            _ => {
+                used.push(name.clone());
+                trailers
+                    .iter()
+                    .filter(|(key, _)| key.eq_ignore_ascii_case(&name))
+                    .map(|(_, value)| value.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            }
+        };
+        rendered.push_str(&value);
+    }
+
+    let trailers = trailers
+        .into_iter()
+        .filter(|(key, _)| !used.iter().any(|name| key.eq_ignore_ascii_case(name)))
+        .collect();
+    Ok((tidy(&rendered), trailers))
+}
+
+/// The ticket ID found in the branch name, e.g. `ABC-123` for `feature/ABC-123-login`.
+fn ticket(message: &Message, branch: &str) -> Result<String, anyhow::Error> {
+    let regex = Regex::new(message.ticket_pattern())
+        .map_err(|e| anyhow!("Invalid message.ticket_pattern: {}", e))?;
+    Ok(regex
+        .captures(branch)
+        .and_then(|captures| captures.get(1).or_else(|| captures.get(0)))
+        .map_or(String::new(), |found| found.as_str().to_string()))
+}
+
+/// Removes the trailing spaces and the blank lines left by the empty placeholders.
+fn tidy(message: &str) -> String {
+    let mut lines: Vec<&str> = vec![];
+    for line in message.lines().map(str::trim_end) {
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    lines.join("\n").trim_end().to_string()
+}
+
+/// The rules of a subject line after a prefix, its length being enforced only when it is
+/// configured.
+pub fn subject_rules(message: &Message, prefix_length: usize) -> Validate {
+    Validate {
+        required: true,
+        max_length: message
+            .subject_max_length
+            .map(|max| max.saturating_sub(prefix_length)),
+        ..Default::default()
+    }
+}
+
+/// Prompts for a subject line after a prefix, warning when it is longer than the default length.
+pub fn prompt_subject(
+    prompt: &impl Prompt,
+    message: &Message,
+    key: &str,
+    initial: &str,
+    prefix_length: usize,
+) -> Result<String, anyhow::Error> {
+    let max = message.subject_max_length().saturating_sub(prefix_length);
+    let rules = subject_rules(message, prefix_length);
+    let subject = prompt.subject(key, initial, &rules, max)?;
+    check_subject(message, prefix_length, &subject)?;
+    Ok(subject)
+}
+
+/// The answers of a message given on the command line, checking its subject against the rules.
+pub fn given(message: &Message, given: &str) -> Result<Answers, anyhow::Error> {
+    let (subject, body) = given.trim().split_once('\n').unwrap_or((given.trim(), ""));
+    check_subject(message, 0, subject)?;
+    Ok(Answers {
+        subject: subject.to_string(),
+        summary: subject.to_string(),
+        body: body.trim().to_string(),
+        ..Default::default()
+    })
+}
+
+/// Checks a subject, or the description of a conventional one after a prefix. A subject longer
+/// than the default maximum length only gets a warning.
+pub fn check_subject(
+    message: &Message,
+    prefix_length: usize,
+    subject: &str,
+) -> Result<(), anyhow::Error> {
+    if subject.trim().is_empty() {
+        return Err(anyhow!("The message is empty"));
+    }
+    let length = subject.chars().count();
+    let max = message.subject_max_length().saturating_sub(prefix_length);
+    if length <= max {
+        return Ok(());
+    }
+    if message.subject_max_length.is_none() {
+        eprintln!(
+            "warning: the subject \"{}\" is {} characters long, more than the recommended {}",
+            subject, length, max
+        );
+        return Ok(());
+    }
+    Err(anyhow!(
+        "The subject \"{}\" is {} characters long, at most {} are allowed",
+        subject,
+        length,
+        max
+    ))
+}
+
+/// Prompts for a body in the editor of git, wrapped at the configured width.
+pub fn prompt_body(
+    prompt: &impl Prompt,
+    git: &impl Git,
+    message: &Message,
+    initial: &str,
+) -> Result<String, anyhow::Error> {
+    let body = prompt.editor("Body (optional):", initial, &git.editor()?)?;
+    Ok(wrap(&body, message.wrap()))
+}
+
+/// Wraps the lines of a body longer than the width, leaving code blocks, indented lines and
+/// words longer than the width, e.g. URLs, intact. The lines of a list item are indented.
+pub fn wrap(body: &str, width: usize) -> String {
+    if width == 0 {
+        return body.to_string();
+    }
+    let mut lines: Vec<String> = vec![];
+    let mut in_code_block = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            lines.push(line.to_string());
+            continue;
+        }
+        let indented = line.starts_with("    ") || line.starts_with('\t');
+        if in_code_block || indented || line.chars().count() <= width {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let indent = " ".repeat(list_marker_length(line));
+        let mut current = String::new();
+        for word in line.split(' ') {
+            let length = current.chars().count() + 1 + word.chars().count();
+            if !current.trim().is_empty() && length > width {
+                lines.push(current);
+                current = indent.clone();
+            } else if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+        lines.push(current);
+    }
+    lines.join("\n")
+}
+
+/// The length of the marker of a list item, e.g. 2 for `- ` or 3 for `1. `.
+fn list_marker_length(line: &str) -> usize {
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+    if trimmed.starts_with("- ") || trimmed.starts_with("* ") {
+        return indent + 2;
+    }
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    match digits > 0 && trimmed[digits..].starts_with(". ") {
+        true => indent + digits + 2,
+        false => indent,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::git::MockGit;
+    use crate::message::{check_subject, check_template, prompt_subject, render, wrap, Answers};
+    use crate::prompt::MockPrompt;
+    use crate::structure::{
+        CommitStructure, Message, MessageStyle, TextTrailer, Trailer, Validate,
+    };
+    use mockall::predicate::eq;
+
+    #[test]
+    fn test_wrap() {
+        let body =
+            "The configuration is read from every ancestor folder, which is slow on network drives.
+- a list item long enough to be wrapped on two lines at the width
+See https://example.org/a/very/long/url/which/must/never/be/split/across/lines
+```
+let code = \"never wrapped, even when it is longer than the width of the body\";
+```
+    an indented line, never wrapped even when it is longer than the width";
+
+        assert_eq!(
+            wrap(body, 40),
+            "The configuration is read from every
+ancestor folder, which is slow on
+network drives.
+- a list item long enough to be wrapped
+  on two lines at the width
+See
+https://example.org/a/very/long/url/which/must/never/be/split/across/lines
+```
+let code = \"never wrapped, even when it is longer than the width of the body\";
+```
+    an indented line, never wrapped even when it is longer than the width"
+        );
+        assert_eq!(wrap(body, 0), body);
+    }
+
+    #[test]
+    fn test_render() {
+        let message = Message {
+            style: MessageStyle::Conventional,
+            template: Some("{type}: {summary}\n\n{body}\n\nRefs: {issue} ({ticket})".to_string()),
+            ..Default::default()
+        };
+        let answers = Answers {
+            subject: "fix: handle an empty branch".to_string(),
+            summary: "handle an empty branch".to_string(),
+            kind: "fix".to_string(),
+            ..Default::default()
+        };
+        let trailers = vec![
+            ("Issue".to_string(), "#42".to_string()),
+            (
+                "Co-authored-by".to_string(),
+                "Jane Doe <jane.doe@example.org>".to_string(),
+            ),
+        ];
+        let mut git: MockGit = MockGit::new();
+        git.expect_current_branch()
+            .times(1)
+            .returning(|| Ok(Some("feature/ABC-123-empty-branch".to_string())));
+
+        let (rendered, trailers) = render(&git, &message, &answers, trailers).unwrap();
+
+        assert_eq!(
+            rendered,
+            "fix: handle an empty branch\n\nRefs: #42 (ABC-123)"
+        );
+        assert_eq!(
+            trailers,
+            vec![(
+                "Co-authored-by".to_string(),
+                "Jane Doe <jane.doe@example.org>".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_check_template() {
+        let structure = |template: &str| CommitStructure {
+            trailers: vec![Trailer::Text(TextTrailer {
+                name: "Issue".to_string(),
+                when: None,
+                validate: Default::default(),
+                default: None,
+            })],
+            message: Message {
+                template: Some(template.to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert!(check_template(&structure("{{{summary}}} {issue} {ticket}")).is_ok());
+        assert_eq!(
+            check_template(&structure("{type}: {summary}"))
+                .unwrap_err()
+                .to_string(),
+            "Unknown placeholder {type} in message.template, expected one of: subject, summary, body, branch, ticket, Issue"
+        );
+        assert!(check_template(&structure("{summary")).is_err());
+    }
+
+    #[test]
+    fn test_check_subject() {
+        let subject = "a".repeat(80);
+        let configured = Message {
+            subject_max_length: Some(50),
+            ..Default::default()
+        };
+
+        assert!(check_subject(&Message::default(), 0, "").is_err());
+        assert!(check_subject(&Message::default(), 0, &subject).is_ok());
+        assert!(check_subject(&configured, 0, &"a".repeat(50)).is_ok());
+        assert_eq!(
+            check_subject(&configured, 10, &"a".repeat(41))
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "The subject \"{}\" is 41 characters long, at most 40 are allowed",
+                "a".repeat(41)
+            )
+        );
+    }
+
+    #[test]
+    fn test_prompt_subject() {
+        let subject = "a".repeat(80);
+        let rules = |max_length: Option<usize>| Validate {
+            required: true,
+            max_length,
+            ..Default::default()
+        };
+        let mut prompt: MockPrompt = MockPrompt::new();
+        // the default length is only shown by the counter
+        prompt
+            .expect_subject()
+            .with(eq("Message:"), eq(""), eq(rules(None)), eq(72))
+            .returning(|_, _, _, _| Ok("a".repeat(80)));
+        prompt
+            .expect_subject()
+            .with(eq("Description:"), eq(""), eq(rules(Some(40))), eq(40))
+            .returning(|_, _, _, _| Ok("a".repeat(40)));
+        let configured = Message {
+            subject_max_length: Some(50),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            prompt_subject(&prompt, &Message::default(), "Message:", "", 0).unwrap(),
+            subject
+        );
+        assert_eq!(
+            prompt_subject(&prompt, &configured, "Description:", "", 10).unwrap(),
+            "a".repeat(40)
+        );
+    }
+}
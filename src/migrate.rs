@@ -0,0 +1,233 @@
+use crate::config;
+use anyhow::anyhow;
+use serde_yaml::Value;
+use std::path::Path;
+
+/// Upgrades a configuration from a version to the next one, describing each change.
+type Migration = fn(&mut Value) -> Vec<String>;
+
+/// The migrations from version 1, the one at index `i` upgrading version `i + 1`.
+const MIGRATIONS: &[Migration] = &[];
+
+/// The latest version of the configuration format.
+pub const CURRENT_VERSION: u32 = 1 + MIGRATIONS.len() as u32;
+
+/// The version of a configuration, 1 when missing, up to the latest of the migrations.
+fn version(value: &Value, migrations: &[Migration]) -> Result<u32, anyhow::Error> {
+    let latest = 1 + migrations.len() as u32;
+    let version = match value.get("version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| *v > 0)
+            .ok_or_else(|| anyhow!("version must be a positive number"))?,
+    };
+    if version > latest {
+        return Err(anyhow!(
+            "version {} is not supported, the latest is {}: upgrade git-bottle",
+            version,
+            latest
+        ));
+    }
+    Ok(version)
+}
+
+/// Upgrades a configuration to the latest version, describing each change.
+pub fn migrate(value: Value) -> Result<(Value, Vec<String>), anyhow::Error> {
+    migrate_with(value, MIGRATIONS)
+}
+
+fn migrate_with(
+    mut value: Value,
+    migrations: &[Migration],
+) -> Result<(Value, Vec<String>), anyhow::Error> {
+    let version = version(&value, migrations)?;
+    let mut changes = vec![];
+    for migration in &migrations[version as usize - 1..] {
+        changes.extend(migration(&mut value));
+    }
+    if !changes.is_empty() {
+        if let Value::Mapping(mapping) = &mut value {
+            mapping.insert(
+                Value::from("version"),
+                Value::from(1 + migrations.len() as u32),
+            );
+        }
+    }
+    Ok((value, changes))
+}
+
+/// The content of a configuration file upgraded to the latest version, with the changes made, or
+/// `None` when it is up to date. YAML files keep their comments when possible, other formats are
+/// serialized again.
+pub fn upgrade(path: &Path, content: &str) -> Result<Option<(String, Vec<String>)>, anyhow::Error> {
+    upgrade_with(path, content, MIGRATIONS)
+}
+
+fn upgrade_with(
+    path: &Path,
+    content: &str,
+    migrations: &[Migration],
+) -> Result<Option<(String, Vec<String>)>, anyhow::Error> {
+    let (migrated, mut changes) = migrate_with(config::parse(path, content)?, migrations)
+        .map_err(|e| anyhow!("Unsupported config file {}: {}", path.display(), e))?;
+    if changes.is_empty() {
+        return Ok(None);
+    }
+    let content = match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::to_string(&migrated).map_err(|e| anyhow!(e))?,
+        Some("json") => format!(
+            "{}\n",
+            serde_json::to_string_pretty(&migrated).map_err(|e| anyhow!(e))?
+        ),
+        _ => match with_version(content, &migrated) {
+            Some(content) => content,
+            None => {
+                changes.push("comments and formatting could not be kept".to_string());
+                serde_yaml::to_string(&migrated).map_err(|e| anyhow!(e))?
+            }
+        },
+    };
+    Ok(Some((content, changes)))
+}
+
+/// The YAML content with the `version` of the migrated configuration, keeping its comments and
+/// formatting, when the version is all the migrations changed.
+fn with_version(content: &str, migrated: &Value) -> Option<String> {
+    let version = format!("version: {}", migrated.get("version")?.as_u64()?);
+    let mut lines: Vec<&str> = content.lines().collect();
+    match lines.iter().position(|l| l.starts_with("version:")) {
+        Some(i) => lines[i] = &version,
+        None => {
+            let first = lines
+                .iter()
+                .position(|l| {
+                    let l = l.trim();
+                    !(l.is_empty() || l.starts_with('#') || l == "---")
+                })
+                .unwrap_or(lines.len());
+            lines.insert(first, &version);
+        }
+    }
+    let content = format!("{}\n", lines.join("\n"));
+    match serde_yaml::from_str::<Value>(&content) {
+        Ok(value) if value == *migrated => Some(content),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::migrate::{self, migrate, migrate_with, upgrade_with, version, Migration};
+    use serde_yaml::Value;
+    use std::path::Path;
+
+    /// Renames the `trailers` key to `fields`.
+    fn rename_trailers(value: &mut Value) -> Vec<String> {
+        match value.as_mapping_mut().and_then(|m| m.remove("trailers")) {
+            Some(trailers) => {
+                value["fields"] = trailers;
+                vec!["`trailers` is now `fields`".to_string()]
+            }
+            None => vec![],
+        }
+    }
+
+    /// Changes the meaning of the configuration, but not its content.
+    fn frequent_order(_: &mut Value) -> Vec<String> {
+        vec!["the values from the git log are now in `frequent` order by default".to_string()]
+    }
+
+    const MIGRATIONS: &[Migration] = &[rename_trailers];
+
+    const CONTENT: &str = "# Our trailers
+trailers: # reviewed every release
+  - name: Issue
+    type: text
+";
+
+    #[test]
+    fn test_migrate() {
+        let (migrated, changes) = migrate_with(
+            serde_yaml::from_str("trailers:\n- name: Issue\n").unwrap(),
+            MIGRATIONS,
+        )
+        .unwrap();
+
+        assert_eq!(changes, vec!["`trailers` is now `fields`"]);
+        assert_eq!(migrated["version"], Value::from(2));
+        assert_eq!(migrated["fields"][0]["name"], Value::from("Issue"));
+        assert!(migrated.get("trailers").is_none());
+    }
+
+    #[test]
+    fn test_migrate_up_to_date() {
+        let value: Value = serde_yaml::from_str("version: 2\nfields: []\n").unwrap();
+
+        let (migrated, changes) = migrate_with(value.clone(), MIGRATIONS).unwrap();
+
+        assert!(changes.is_empty());
+        assert_eq!(migrated, value);
+        assert!(migrate(serde_yaml::from_str("trailers: []").unwrap())
+            .unwrap()
+            .1
+            .is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let current = migrate::MIGRATIONS;
+
+        assert!(version(&serde_yaml::from_str("version: 2").unwrap(), current).is_err());
+        assert!(version(&serde_yaml::from_str("version: 0").unwrap(), current).is_err());
+        assert_eq!(
+            version(&serde_yaml::from_str("trailers: []").unwrap(), current).unwrap(),
+            1
+        );
+        assert_eq!(
+            version(&serde_yaml::from_str("version: 2").unwrap(), MIGRATIONS).unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_upgrade_keeps_the_comments() {
+        let (content, changes) = upgrade_with(Path::new("test.yml"), CONTENT, &[frequent_order])
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            content,
+            "# Our trailers\nversion: 2\ntrailers: # reviewed every release\n  - name: Issue\n    type: text\n"
+        );
+        assert_eq!(
+            changes,
+            vec!["the values from the git log are now in `frequent` order by default"]
+        );
+        assert!(
+            upgrade_with(Path::new("test.yml"), &content, &[frequent_order])
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_upgrade_without_the_comments() {
+        let (content, changes) = upgrade_with(Path::new("test.yml"), CONTENT, MIGRATIONS)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            content,
+            "fields:\n- name: Issue\n  type: text\nversion: 2\n"
+        );
+        assert_eq!(
+            changes,
+            vec![
+                "`trailers` is now `fields`",
+                "comments and formatting could not be kept"
+            ]
+        );
+    }
+}
@@ -1,59 +1,131 @@
+use crate::check::{check_count, check_value, Violation};
+use crate::structure::Validate;
 use anyhow::anyhow;
-use inquire::{MultiSelect, Select, Text};
+use inquire::autocompletion::{Autocomplete, Replacement};
+use inquire::list_option::ListOption;
+use inquire::validator::Validation;
+use inquire::{Confirm, CustomUserError, Editor, MultiSelect, Select, Text};
+use std::cell::RefCell;
+use std::ffi::OsStr;
+use std::rc::Rc;
 
 #[cfg_attr(test, mockall::automock)]
 pub trait Prompt {
-    fn text(&self, key: &str) -> Result<String, anyhow::Error>;
-    fn multi_text(&self, key: &str) -> Result<Vec<String>, anyhow::Error>;
+    fn text(&self, key: &str, initial: &str, validate: &Validate) -> Result<String, anyhow::Error>;
+    /// Prompts for a subject line, with a counter of its length up to `max_length`, which is only
+    /// enforced by the rules.
+    fn subject(
+        &self,
+        key: &str,
+        initial: &str,
+        validate: &Validate,
+        max_length: usize,
+    ) -> Result<String, anyhow::Error>;
+    fn multi_text(&self, key: &str, validate: &Validate) -> Result<Vec<String>, anyhow::Error>;
+    fn editor(&self, key: &str, initial: &str, command: &str) -> Result<String, anyhow::Error>;
+    fn confirm(&self, key: &str, default: bool) -> Result<bool, anyhow::Error>;
     fn select(
         &self,
         key: &str,
         options: Vec<String>,
         allow_other: bool,
+        default: Option<usize>,
+        validate: &Validate,
     ) -> Result<String, anyhow::Error>;
     fn multi_select(
         &self,
         key: &str,
         options: Vec<String>,
         allow_other: bool,
+        defaults: Vec<usize>,
+        validate: &Validate,
     ) -> Result<Vec<String>, anyhow::Error>;
 }
 
 pub struct PromptInquire {}
 
 impl Prompt for PromptInquire {
-    fn text(&self, key: &str) -> Result<String, anyhow::Error> {
-        Text::new(key).prompt().map_err(|e| anyhow!(e))
+    fn text(&self, key: &str, initial: &str, validate: &Validate) -> Result<String, anyhow::Error> {
+        text(key, initial, validate, validate.max_length)
+    }
+
+    fn subject(
+        &self,
+        key: &str,
+        initial: &str,
+        validate: &Validate,
+        max_length: usize,
+    ) -> Result<String, anyhow::Error> {
+        text(key, initial, validate, Some(max_length))
+    }
+
+    fn editor(&self, key: &str, initial: &str, command: &str) -> Result<String, anyhow::Error> {
+        let mut words = command.split_whitespace().map(OsStr::new);
+        let program = words.next().unwrap_or(OsStr::new("vi"));
+        let args: Vec<&OsStr> = words.collect();
+        Editor::new(key)
+            .with_predefined_text(initial)
+            .with_editor_command(program)
+            .with_args(&args)
+            .with_file_extension(".txt")
+            .with_help_message("e to open the editor, enter to submit")
+            .prompt()
+            .map(|text| text.trim().to_string())
+            .map_err(|e| anyhow!(e))
     }
 
-    fn multi_text(&self, key: &str) -> Result<Vec<String>, anyhow::Error> {
+    fn multi_text(&self, key: &str, validate: &Validate) -> Result<Vec<String>, anyhow::Error> {
         let mut values: Vec<String> = vec![];
         loop {
-            let value = self.text(key)?;
+            let rules = Validate {
+                required: validate.required && values.is_empty(),
+                max_count: None,
+                ..validate.clone()
+            };
+            let value = self.text(key, "", &rules)?;
             if value.is_empty() {
                 break;
             }
             values.push(value);
+            if validate.max_count.is_some_and(|max| values.len() >= max) {
+                break;
+            }
         }
         Ok(values)
     }
 
+    fn confirm(&self, key: &str, default: bool) -> Result<bool, anyhow::Error> {
+        Confirm::new(key)
+            .with_default(default)
+            .prompt()
+            .map_err(|e| anyhow!(e))
+    }
+
     fn select(
         &self,
         key: &str,
         options: Vec<String>,
         allow_other: bool,
+        default: Option<usize>,
+        validate: &Validate,
     ) -> Result<String, anyhow::Error> {
+        let starting_cursor = default.unwrap_or_default();
         if allow_other {
             let mut options = options.clone();
             options.push("<other>".to_string());
-            let value = Select::new(key, options).prompt().map_err(|e| anyhow!(e))?;
+            let value = Select::new(key, options)
+                .with_starting_cursor(starting_cursor)
+                .prompt()
+                .map_err(|e| anyhow!(e))?;
             if value.eq("<other>") {
-                return self.text(key);
+                return self.text(key, "", validate);
             }
             Ok(value)
         } else {
-            Select::new(key, options).prompt().map_err(|e| anyhow!(e))
+            Select::new(key, options)
+                .with_starting_cursor(starting_cursor)
+                .prompt()
+                .map_err(|e| anyhow!(e))
         }
     }
 
@@ -62,22 +134,148 @@ impl Prompt for PromptInquire {
         key: &str,
         options: Vec<String>,
         allow_other: bool,
+        defaults: Vec<usize>,
+        validate: &Validate,
     ) -> Result<Vec<String>, anyhow::Error> {
+        let (name, rules) = (trailer_name(key), validate.clone());
+        let validator = move |selected: &[ListOption<&String>]| {
+            // `<other>` stands for one typed value at least
+            Ok(validation(check_count(&name, &rules, selected.len())))
+        };
         if allow_other {
             let mut options = options.clone();
             options.push("<other>".to_string());
             let mut values = MultiSelect::new(key, options)
+                .with_default(&defaults)
+                .with_validator(validator)
                 .prompt()
                 .map_err(|e| anyhow!(e))?;
             if values.contains(&"<other>".to_string()) {
                 values.retain(|x| x != "<other>");
-                values.append(&mut self.multi_text(key)?);
+                let rules = Validate {
+                    required: true,
+                    max_count: validate
+                        .max_count
+                        .map(|max| max.saturating_sub(values.len())),
+                    ..validate.clone()
+                };
+                values.append(&mut self.multi_text(key, &rules)?);
             }
             Ok(values)
         } else {
             MultiSelect::new(key, options)
+                .with_default(&defaults)
+                .with_validator(validator)
                 .prompt()
                 .map_err(|e| anyhow!(e))
         }
     }
 }
+
+/// Prompts for a single line of text, with a counter of its length when there is a maximum.
+fn text(
+    key: &str,
+    initial: &str,
+    validate: &Validate,
+    max_length: Option<usize>,
+) -> Result<String, anyhow::Error> {
+    let (name, rules) = (trailer_name(key), validate.clone());
+    let counter = max_length.map(|max| Counter {
+        max,
+        input: Rc::new(RefCell::new(initial.to_string())),
+    });
+    let answer = counter.clone();
+    let mut text = Text::new(key)
+        .with_initial_value(initial)
+        .with_validator(move |value: &str| {
+            let value = answer
+                .as_ref()
+                .map_or(value.to_string(), |c| c.answer(value));
+            let count = usize::from(!value.is_empty());
+            let mut errors = check_count(&name, &rules, count);
+            if count > 0 {
+                errors.extend(check_value(&name, &rules, &value)?);
+            }
+            Ok(validation(errors))
+        });
+    if let Some(counter) = &counter {
+        text = text
+            .with_autocomplete(counter.clone())
+            .with_help_message("enter to submit");
+    }
+    let value = text.prompt().map_err(|e| anyhow!(e))?;
+    Ok(counter.map_or(value.clone(), |c| c.answer(&value)))
+}
+
+/// The name of the trailer asked by a prompt, e.g. `Issue` for `Issue:`.
+fn trailer_name(key: &str) -> String {
+    key.trim_end_matches(':').to_string()
+}
+
+/// Shows the number of characters of a text while it is typed, as its only suggestion.
+#[derive(Clone)]
+struct Counter {
+    max: usize,
+    input: Rc<RefCell<String>>,
+}
+
+impl Counter {
+    fn label(&self, input: &str) -> String {
+        format!("{}/{} characters", input.chars().count(), self.max)
+    }
+
+    /// The text typed, which inquire replaces by the counter when it is highlighted.
+    fn answer(&self, value: &str) -> String {
+        let input = self.input.borrow().clone();
+        match value == self.label(&input) {
+            true => input,
+            false => value.to_string(),
+        }
+    }
+}
+
+impl Autocomplete for Counter {
+    fn get_suggestions(&mut self, input: &str) -> Result<Vec<String>, CustomUserError> {
+        *self.input.borrow_mut() = input.to_string();
+        Ok(vec![self.label(input)])
+    }
+
+    fn get_completion(
+        &mut self,
+        _input: &str,
+        _highlighted_suggestion: Option<String>,
+    ) -> Result<Replacement, CustomUserError> {
+        Ok(None)
+    }
+}
+
+fn validation(errors: Vec<Violation>) -> Validation {
+    match errors.first() {
+        None => Validation::Valid,
+        Some(error) => Validation::Invalid(error.to_string().into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prompt::Counter;
+    use inquire::autocompletion::Autocomplete;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_counter() {
+        let mut counter = Counter {
+            max: 5,
+            input: Rc::new(RefCell::new(String::new())),
+        };
+
+        assert_eq!(
+            counter.get_suggestions("Fix the build").unwrap(),
+            vec!["13/5 characters"]
+        );
+        // the counter is checked and answered as the text typed when it is highlighted
+        assert_eq!(counter.answer("13/5 characters"), "Fix the build");
+        assert_eq!(counter.answer("Fix"), "Fix");
+    }
+}
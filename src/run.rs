@@ -1,88 +1,469 @@
-use crate::git::Git;
+use crate::authors;
+use crate::check::{check_trailer, check_value, is_identity_trailer};
+use crate::conventional;
+use crate::git::{Git, LogEntry};
+use crate::identity;
+use crate::message::{self, Answers};
 use crate::prompt::Prompt;
-use crate::structure::{CommitStructure, Trailer, Values};
+use crate::session;
+use crate::structure::{
+    CommitStructure, DefaultFrom, DefaultValue, Message, MessageStyle, Order, Trailer, Validate,
+    Values, ValuesFromGitLog,
+};
+use crate::trailers;
+use crate::when;
+use anyhow::anyhow;
 use inquire::ui::{Color, RenderConfig, StyleSheet, Styled};
+use regex::Regex;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// A message to start from, e.g. the one of the commit being amended.
+#[derive(Debug, Default)]
+pub struct Draft {
+    pub message: String,
+    pub trailers: Vec<(String, String)>,
+}
+
+impl Draft {
+    pub fn parse(message: &str, config: &trailers::Config) -> Draft {
+        let (message, trailers) = trailers::split(message, config);
+        Draft { message, trailers }
+    }
+}
+
+/// Answers given on the command line, e.g. by `-m` and `--trailer`, which are not prompted for.
+#[derive(Debug, Default)]
+pub struct Given {
+    pub message: Option<String>,
+    /// The trailers, in the order they were given
+    pub trailers: Vec<(String, String)>,
+    /// Whether to fail instead of prompting, taking the existing or default values of the trailers
+    /// which are not given
+    pub no_interactive: bool,
+}
+
+impl Given {
+    fn values(&self, name: &str) -> Option<Vec<String>> {
+        let values: Vec<String> = self
+            .trailers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+            .collect();
+        (!values.is_empty()).then_some(values)
+    }
+}
 
 pub fn run(
     prompt: &impl Prompt,
     git: &impl Git,
     commit_structure: CommitStructure,
+    draft: &Draft,
+    given: &Given,
 ) -> Result<String, anyhow::Error> {
     inquire::set_global_render_config(get_render_config());
 
-    let message = prompt.text("Message:")?;
+    let config = commit_structure.message.clone();
+    let given_message = match &given.message {
+        Some(message) => Some(message.as_str()),
+        // the message of the commit being amended
+        None if given.no_interactive && !draft.message.trim().is_empty() => Some(&*draft.message),
+        None if given.no_interactive => {
+            return Err(anyhow!("A message is required, give it with -m"));
+        }
+        None => None,
+    };
+    let (answers, mut all_trailers, existing) = match config.style {
+        MessageStyle::Free => {
+            let answers = match given_message {
+                Some(message) => message::given(&config, message)?,
+                None => free_message(prompt, git, &config, draft)?,
+            };
+            (answers, vec![], draft.trailers.clone())
+        }
+        MessageStyle::Conventional if given_message.is_some() => {
+            let answers = conventional::given(&config, given_message.unwrap_or_default())?;
+            (answers, vec![], draft.trailers.clone())
+        }
+        MessageStyle::Conventional => {
+            let (answers, breaking_change) =
+                conventional::prompt_message(prompt, git, &config, draft)?;
+            let mut existing = draft.trailers.clone();
+            existing.retain(|(key, _)| !conventional::is_breaking_change(key));
+            (answers, breaking_change, existing)
+        }
+    };
+    all_trailers.extend(trailers(prompt, git, commit_structure, &existing, given)?);
+    let (message, all_trailers) = message::render(git, &config, &answers, all_trailers)?;
+    compose(git, &message, &all_trailers)
+}
+
+fn free_message(
+    prompt: &impl Prompt,
+    git: &impl Git,
+    config: &Message,
+    draft: &Draft,
+) -> Result<Answers, anyhow::Error> {
+    let (subject, body) = draft
+        .message
+        .split_once('\n')
+        .unwrap_or((&draft.message, ""));
+    let subject = message::prompt_subject(prompt, config, "Message:", subject, 0)?;
+    let body = match config.body || message::uses(config, "body") {
+        true => message::prompt_body(prompt, git, config, body.trim())?,
+        false => body.to_string(),
+    };
+    Ok(Answers {
+        summary: subject.clone(),
+        subject,
+        body,
+        ..Default::default()
+    })
+}
+
+/// Adds the trailers to the message the way `git commit --trailer` does.
+pub fn compose(
+    git: &impl Git,
+    message: &str,
+    trailers: &[(String, String)],
+) -> Result<String, anyhow::Error> {
+    let config = trailers::Config::read(git)?;
+    Ok(trailers::interpret(message, trailers, &config))
+}
 
-    let mut all_trailers: Vec<String> = vec![];
-    for trailer in commit_structure.trailers {
+/// Prompts for the trailers whose conditions hold and which are not given, pre-selecting the
+/// existing ones. Existing and given trailers which are not in the configuration are kept as they
+/// are.
+pub fn trailers(
+    prompt: &impl Prompt,
+    git: &impl Git,
+    commit_structure: CommitStructure,
+    existing: &[(String, String)],
+    given: &Given,
+) -> Result<Vec<(String, String)>, anyhow::Error> {
+    inquire::set_global_render_config(get_render_config());
+
+    let now = session::now();
+    let mut context = when::Context::new(git);
+    let mut all_trailers: Vec<(String, String)> = vec![];
+    for trailer in &commit_structure.trailers {
+        if let Some(values) = given.values(trailer.name()) {
+            let values = match is_identity_trailer(trailer.name(), trailer.validate()) {
+                true => to_identities(&commit_structure.authors, values),
+                false => values,
+            };
+            add_trailers(
+                &mut all_trailers,
+                trailer.name(),
+                &checked(trailer, values)?,
+            );
+            continue;
+        }
+        if let Some(when) = trailer.when() {
+            if !context.holds(when, &all_trailers)? {
+                // the existing values are kept, e.g. when amending on another branch
+                let current = existing_values(existing, trailer.name());
+                add_trailers(&mut all_trailers, trailer.name(), &current);
+                continue;
+            }
+        }
+        let validate = trailer.validate();
+        let current = match existing_values(existing, trailer.name()) {
+            current if current.is_empty() => defaults(git, trailer)?,
+            current => current,
+        };
+        if given.no_interactive {
+            let current = match trailer {
+                Trailer::MultiSelect(_) => current,
+                _ => current.into_iter().take(1).collect(),
+            };
+            add_trailers(
+                &mut all_trailers,
+                trailer.name(),
+                &checked(trailer, current)?,
+            );
+            continue;
+        }
         match trailer {
             Trailer::Text(m) => {
                 let key = format!("{}:", m.name);
-                let value = prompt.text(&key)?;
-                add_trailer(&mut all_trailers, &key, &value);
+                let initial = current.first().map(String::as_str).unwrap_or_default();
+                let value = prompt.text(&key, initial, validate)?;
+                add_trailer(&mut all_trailers, &m.name, &value);
             }
             Trailer::MultiSelect(m) => match &m.values {
                 Values::FromOptions(v) => {
                     let key = format!("{}:", m.name);
-                    let values = prompt.multi_select(&key, v.options.clone(), false)?;
-                    add_trailers(&mut all_trailers, &key, &values);
+                    let (options, defaults) = with_current(v.options.clone(), &current);
+                    let values = prompt.multi_select(&key, options, false, defaults, validate)?;
+                    add_trailers(&mut all_trailers, &m.name, &values);
                 }
                 Values::FromGitLog(v) => {
                     let key = format!("{}:", m.name);
-                    let options = find_in_git_log(git, &v.format_strings, &v.max_count)?;
+                    let choices = find_in_git_log(git, v, &commit_structure.identities, now)?;
+                    let choices = valid_choices(&m.name, validate, choices)?;
+                    let (options, defaults) =
+                        with_current(labels(&choices), &to_labels(&choices, &current));
                     if options.is_empty() {
-                        let values = prompt.multi_text(&key)?;
-                        add_trailers(&mut all_trailers, &key, &values);
+                        let values = prompt.multi_text(&key, validate)?;
+                        add_trailers(&mut all_trailers, &m.name, &values);
                     } else {
-                        let values = prompt.multi_select(&key, options, true)?;
-                        add_trailers(&mut all_trailers, &key, &values);
+                        let values =
+                            prompt.multi_select(&key, options, true, defaults, validate)?;
+                        add_trailers(&mut all_trailers, &m.name, &from_labels(&choices, values));
+                    }
+                }
+                Values::FromAuthors(_) => {
+                    let key = format!("{}:", m.name);
+                    let authors = &commit_structure.authors;
+                    let choices = authors::choices(authors);
+                    let (options, defaults) =
+                        with_current(labels(&choices), &to_labels(&choices, &current));
+                    if options.is_empty() {
+                        let values = prompt.multi_text(&key, validate)?;
+                        add_trailers(&mut all_trailers, &m.name, &to_identities(authors, values));
+                    } else {
+                        let values =
+                            prompt.multi_select(&key, options, true, defaults, validate)?;
+                        add_trailers(&mut all_trailers, &m.name, &to_identities(authors, values));
                     }
                 }
             },
             Trailer::Select(m) => match &m.values {
                 Values::FromOptions(v) => {
                     let key = format!("{}:", m.name);
-                    let value = prompt.select(&key, v.options.clone(), false);
-                    add_trailer(&mut all_trailers, &key, &value?);
+                    let (options, defaults) = with_current(v.options.clone(), &current);
+                    let value =
+                        prompt.select(&key, options, false, defaults.first().copied(), validate);
+                    add_trailer(&mut all_trailers, &m.name, &value?);
                 }
                 Values::FromGitLog(v) => {
                     let key = format!("{}:", m.name);
-                    let options = find_in_git_log(git, &v.format_strings, &v.max_count)?;
+                    let choices = find_in_git_log(git, v, &commit_structure.identities, now)?;
+                    let choices = valid_choices(&m.name, validate, choices)?;
+                    let (options, defaults) =
+                        with_current(labels(&choices), &to_labels(&choices, &current));
                     if options.is_empty() {
-                        let value = prompt.text(&key)?;
-                        add_trailer(&mut all_trailers, &key, &value);
+                        let value = prompt.text(&key, "", validate)?;
+                        add_trailer(&mut all_trailers, &m.name, &value);
                     } else {
-                        let value = prompt.select(&key, options, true)?;
-                        add_trailer(&mut all_trailers, &key, &value);
+                        let value = prompt.select(
+                            &key,
+                            options,
+                            true,
+                            defaults.first().copied(),
+                            validate,
+                        )?;
+                        add_trailers(
+                            &mut all_trailers,
+                            &m.name,
+                            &from_labels(&choices, vec![value]),
+                        );
                     }
                 }
+                Values::FromAuthors(_) => {
+                    let key = format!("{}:", m.name);
+                    let authors = &commit_structure.authors;
+                    let choices = authors::choices(authors);
+                    let (options, defaults) =
+                        with_current(labels(&choices), &to_labels(&choices, &current));
+                    let value = if options.is_empty() {
+                        prompt.text(&key, "", validate)?
+                    } else {
+                        prompt.select(&key, options, true, defaults.first().copied(), validate)?
+                    };
+                    add_trailers(
+                        &mut all_trailers,
+                        &m.name,
+                        &to_identities(authors, vec![value]),
+                    );
+                }
             },
         }
     }
-    let lines: Vec<String> = vec![vec![message, "".to_string()], all_trailers]
+
+    for (key, value) in existing {
+        let configured = commit_structure
+            .trailers
+            .iter()
+            .any(|t| t.name().eq_ignore_ascii_case(key));
+        if !configured {
+            add_trailer(&mut all_trailers, key, value);
+        }
+    }
+    for (key, value) in &given.trailers {
+        let configured = commit_structure
+            .trailers
+            .iter()
+            .any(|t| t.name().eq_ignore_ascii_case(key));
+        if configured {
+            continue;
+        }
+        let values = match is_identity_trailer(key, &Validate::default()) {
+            true => to_identities(&commit_structure.authors, vec![value.clone()]),
+            false => vec![value.clone()],
+        };
+        for value in &values {
+            let violations = check_value(key, &Validate::default(), value)?;
+            if let Some(violation) = violations.first() {
+                return Err(anyhow!("Invalid answer: {}", violation));
+            }
+        }
+        add_trailers(&mut all_trailers, key, &values);
+    }
+    Ok(all_trailers)
+}
+
+/// The values of a trailer which are not prompted for, once checked against its definition.
+fn checked(trailer: &Trailer, values: Vec<String>) -> Result<Vec<String>, anyhow::Error> {
+    let violations = check_trailer(trailer, &values)?;
+    match violations.is_empty() {
+        true => Ok(values),
+        false => {
+            let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+            Err(anyhow!("Invalid answer: {}", violations.join(", ")))
+        }
+    }
+}
+
+fn existing_values(existing: &[(String, String)], name: &str) -> Vec<String> {
+    existing
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.clone())
+        .collect()
+}
+
+/// Leaves out the choices whose value breaks the rules of the trailer.
+fn valid_choices(
+    name: &str,
+    validate: &Validate,
+    choices: Vec<(String, String)>,
+) -> Result<Vec<(String, String)>, anyhow::Error> {
+    let mut valid = vec![];
+    for choice in choices {
+        if check_value(name, validate, &choice.1)?.is_empty() {
+            valid.push(choice);
+        }
+    }
+    Ok(valid)
+}
+
+/// The values of the `default` of a trailer, pre-selected before any answer.
+fn defaults(git: &impl Git, trailer: &Trailer) -> Result<Vec<String>, anyhow::Error> {
+    let dynamic = match trailer.default() {
+        None => return Ok(vec![]),
+        Some(DefaultValue::Value(value)) => return Ok(vec![value.clone()]),
+        Some(DefaultValue::Values(values)) => return Ok(values.clone()),
+        Some(DefaultValue::Dynamic(dynamic)) => dynamic,
+    };
+    let values = match dynamic.from {
+        // there is no last commit in a new repository
+        DefaultFrom::LastCommit => match git.message("HEAD") {
+            Ok(message) => existing_values(
+                &trailers::parse(&message, &trailers::Config::read(git)?),
+                trailer.name(),
+            ),
+            Err(_) => vec![],
+        },
+        DefaultFrom::Branch => git.current_branch()?.into_iter().collect(),
+    };
+    let Some(pattern) = &dynamic.pattern else {
+        return Ok(values);
+    };
+    let regex = Regex::new(pattern).map_err(|e| {
+        anyhow!(
+            "Invalid default pattern for trailer {}: {}",
+            trailer.name(),
+            e
+        )
+    })?;
+    Ok(values
+        .iter()
+        .filter_map(|value| {
+            let captures = regex.captures(value)?;
+            let found = captures.get(1).or_else(|| captures.get(0))?;
+            Some(found.as_str().to_string())
+        })
+        .collect())
+}
+
+/// Adds the current values to the options, returning the indices of the current values.
+fn with_current(mut options: Vec<String>, current: &[String]) -> (Vec<String>, Vec<usize>) {
+    let mut indices = vec![];
+    for value in current {
+        let index = match options.iter().position(|o| o == value) {
+            Some(index) => index,
+            None => {
+                options.push(value.clone());
+                options.len() - 1
+            }
+        };
+        if !indices.contains(&index) {
+            indices.push(index);
+        }
+    }
+    (options, indices)
+}
+
+fn labels(choices: &[(String, String)]) -> Vec<String> {
+    choices.iter().map(|(label, _)| label.clone()).collect()
+}
+
+/// Maps the values to the labels of their choices.
+fn to_labels(choices: &[(String, String)], values: &[String]) -> Vec<String> {
+    values
+        .iter()
+        .map(|value| {
+            choices
+                .iter()
+                .find(|(_, v)| v == value)
+                .map_or(value.clone(), |(label, _)| label.clone())
+        })
+        .collect()
+}
+
+/// Maps the selected labels to the values of their choices, keeping the typed values.
+fn from_labels(choices: &[(String, String)], labels: Vec<String>) -> Vec<String> {
+    labels
         .into_iter()
-        .flatten()
-        .collect();
-    Ok(lines.join("\n"))
+        .map(|label| {
+            choices
+                .iter()
+                .find(|(l, _)| *l == label)
+                .map_or(label, |(_, value)| value.clone())
+        })
+        .collect()
 }
 
-fn add_trailer(trailers: &mut Vec<String>, key: &str, value: &str) {
+/// Maps the selected labels, or the typed handles, to their identities.
+fn to_identities(authors: &BTreeMap<String, String>, values: Vec<String>) -> Vec<String> {
+    from_labels(&authors::choices(authors), values)
+        .into_iter()
+        .map(|value| authors::expand(authors, &value).cloned().unwrap_or(value))
+        .collect()
+}
+
+fn add_trailer(trailers: &mut Vec<(String, String)>, name: &str, value: &str) {
     if !value.is_empty() {
-        trailers.push(format!("{} {}", key, value));
+        trailers.push((name.to_string(), value.to_string()));
     }
 }
 
-fn add_trailers(trailers: &mut Vec<String>, key: &str, values: &[String]) {
-    if !values.is_empty() {
-        trailers.push(
-            values
-                .iter()
-                .map(|v| format!("{} {}", key, v))
-                .collect::<Vec<String>>()
-                .join("\n"),
-        );
+fn add_trailers(trailers: &mut Vec<(String, String)>, name: &str, values: &[String]) {
+    for value in values {
+        add_trailer(trailers, name, value);
     }
 }
 
+pub fn format_trailers(trailers: &[(String, String)]) -> Vec<String> {
+    trailers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect()
+}
+
 fn get_render_config() -> RenderConfig {
     RenderConfig {
         prompt_prefix: Styled::new(">"),
@@ -93,53 +474,205 @@ fn get_render_config() -> RenderConfig {
     }
 }
 
+/// The values found in the git log, as `(label, value)` in the configured order, with a single
+/// identity per human.
 fn find_in_git_log(
     git: &impl Git,
-    format_strings: &[String],
-    max_count: &Option<i32>,
-) -> Result<Vec<String>, anyhow::Error> {
-    let mut values = vec![];
-    for format_string in format_strings {
-        values.append(&mut git.log(format_string, max_count)?);
-    }
-    values.sort();
-    values.dedup();
-    Ok(values)
+    values: &ValuesFromGitLog,
+    equivalences: &BTreeMap<String, Vec<String>>,
+    now: u64,
+) -> Result<Vec<(String, String)>, anyhow::Error> {
+    if values.order == Order::Alpha {
+        let mut found = vec![];
+        for format_string in &values.format_strings {
+            found.append(&mut git.log(format_string, &values.max_count)?);
+        }
+        found.sort();
+        found.dedup();
+        let (canonical, me) = identities(git, &found, equivalences, values.exclude_self)?;
+        let mut found: Vec<String> = found
+            .into_iter()
+            .map(|v| canonical.get(&v).cloned().unwrap_or(v))
+            .filter(|v| Some(v) != me.as_ref())
+            .collect();
+        found.sort();
+        found.dedup();
+        return Ok(found.into_iter().map(|v| (v.clone(), v)).collect());
+    }
+
+    let half_life = match &values.half_life {
+        Some(half_life) => Some(session::parse_duration(half_life)?),
+        None => None,
+    };
+    let mut entries = vec![];
+    for format_string in &values.format_strings {
+        entries.append(&mut git.log_entries(format_string, &values.max_count)?);
+    }
+    let found: Vec<String> = entries.iter().map(|e| e.value.clone()).collect();
+    let (canonical, me) = identities(git, &found, equivalences, values.exclude_self)?;
+    for entry in &mut entries {
+        if let Some(value) = canonical.get(&entry.value) {
+            entry.value = value.clone();
+        }
+    }
+    entries.retain(|e| Some(&e.value) != me.as_ref());
+    Ok(rank(&entries, values.order, half_life, now)
+        .into_iter()
+        .map(|r| (r.label(now), r.value))
+        .collect())
+}
+
+/// The canonical spellings of the identities found, and the one of the committer when excluded.
+fn identities(
+    git: &impl Git,
+    found: &[String],
+    equivalences: &BTreeMap<String, Vec<String>>,
+    exclude_self: bool,
+) -> Result<(BTreeMap<String, String>, Option<String>), anyhow::Error> {
+    if !found.iter().any(|v| identity::parse(v).is_some()) {
+        return Ok((BTreeMap::new(), None));
+    }
+    let me = match exclude_self {
+        true => committer(git)?,
+        false => None,
+    };
+    let mut values = found.to_vec();
+    values.extend(me.clone());
+    let canonical = identity::canonical(git, &values, equivalences)?;
+    let me = me.and_then(|me| canonical.get(&me).cloned());
+    Ok((canonical, me))
+}
+
+/// The identity of the committer, from the git configuration.
+fn committer(git: &impl Git) -> Result<Option<String>, anyhow::Error> {
+    let name = git.config_get("user.name")?;
+    let email = git.config_get("user.email")?;
+    Ok(match (name, email) {
+        (Some(name), Some(email)) => Some(format!("{} <{}>", name, email)),
+        _ => None,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+struct Ranked {
+    value: String,
+    count: usize,
+    last: u64,
+    score: f64,
+}
+
+impl Ranked {
+    /// The value annotated with its usage, e.g. `Jane Doe <…> — 14 commits, last 2d ago`.
+    fn label(&self, now: u64) -> String {
+        format!(
+            "{} — {} commit{}, last {} ago",
+            self.value,
+            self.count,
+            if self.count == 1 { "" } else { "s" },
+            format_age(now.saturating_sub(self.last))
+        )
+    }
+}
+
+/// Counts the occurrences of the values, each weighted by `0.5^(age / half_life)` if any.
+fn rank(entries: &[LogEntry], order: Order, half_life: Option<u64>, now: u64) -> Vec<Ranked> {
+    let mut ranked: Vec<Ranked> = vec![];
+    for entry in entries {
+        let weight = match half_life {
+            Some(half_life) if half_life > 0 => {
+                let age = now.saturating_sub(entry.timestamp) as f64;
+                0.5_f64.powf(age / half_life as f64)
+            }
+            _ => 1.0,
+        };
+        match ranked.iter_mut().find(|r| r.value == entry.value) {
+            Some(r) => {
+                r.count += 1;
+                r.last = r.last.max(entry.timestamp);
+                r.score += weight;
+            }
+            None => ranked.push(Ranked {
+                value: entry.value.clone(),
+                count: 1,
+                last: entry.timestamp,
+                score: weight,
+            }),
+        }
+    }
+    match order {
+        Order::Frequent => ranked.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.last.cmp(&a.last))
+        }),
+        _ => ranked.sort_by_key(|r| Reverse(r.last)),
+    }
+    ranked
+}
+
+/// Formats an age in seconds with its largest unit, e.g. `2d`, `3h` or `5m`.
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        s if s >= 86400 => format!("{}d", s / 86400),
+        s if s >= 3600 => format!("{}h", s / 3600),
+        s => format!("{}m", s / 60),
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::git::LogEntry;
     use crate::git::MockGit;
     use crate::prompt::MockPrompt;
-    use crate::run::run;
+    use crate::run::{rank, run, Draft, Given};
     use crate::structure::{
-        CommitStructure, MultiSelectTrailer, SelectTrailer, TextTrailer, Trailer, Values,
-        ValuesFromGitLog, ValuesFromOptions,
+        CommitStructure, DefaultFrom, DefaultValue, DynamicDefault, MultiSelectTrailer, Order,
+        SelectTrailer, TextTrailer, Trailer, Validate, Values, ValuesFromAuthors, ValuesFromGitLog,
+        ValuesFromOptions, When,
     };
-    use mockall::predicate::eq;
+    use crate::trailers::Config;
+    use mockall::predicate::{always, eq};
+    use std::collections::BTreeMap;
 
     #[test]
     fn test_text() {
         let commit_structure = CommitStructure {
             trailers: vec![Trailer::Text(TextTrailer {
                 name: "Issue".to_string(),
+                when: None,
+                validate: Validate::default(),
+                default: None,
             })],
+            ..Default::default()
         };
 
         let mut prompt: MockPrompt = MockPrompt::new();
-        let git: MockGit = MockGit::new();
+        let mut git: MockGit = MockGit::new();
+        git.expect_config_get_regexp().returning(|_| Ok(vec![]));
 
         prompt
-            .expect_text()
-            .with(eq("Message:".to_string()))
-            .returning(|_| Ok("First commit".to_string()));
+            .expect_subject()
+            .with(
+                eq("Message:".to_string()),
+                eq("".to_string()),
+                always(),
+                always(),
+            )
+            .returning(|_, _, _, _| Ok("First commit".to_string()));
 
         prompt
             .expect_text()
-            .with(eq("Issue:".to_string()))
-            .returning(|_| Ok("#42".to_string()));
+            .with(eq("Issue:".to_string()), eq("".to_string()), always())
+            .returning(|_, _, _| Ok("#42".to_string()));
 
-        let commit_message = run(&prompt, &git, commit_structure).unwrap();
+        let commit_message = run(
+            &prompt,
+            &git,
+            commit_structure,
+            &Draft::default(),
+            &Given::default(),
+        )
+        .unwrap();
 
         assert_eq!(commit_message, "First commit\n\nIssue: #42".to_string())
     }
@@ -149,19 +682,29 @@ mod test {
         let commit_structure = CommitStructure {
             trailers: vec![Trailer::Select(SelectTrailer {
                 name: "Issue".to_string(),
+                when: None,
+                validate: Validate::default(),
+                default: None,
                 values: Values::FromOptions(ValuesFromOptions {
                     options: vec!["#1".to_string(), "#2".to_string()],
                 }),
             })],
+            ..Default::default()
         };
 
         let mut prompt: MockPrompt = MockPrompt::new();
-        let git: MockGit = MockGit::new();
+        let mut git: MockGit = MockGit::new();
+        git.expect_config_get_regexp().returning(|_| Ok(vec![]));
 
         prompt
-            .expect_text()
-            .with(eq("Message:".to_string()))
-            .returning(|_| Ok("First commit".to_string()));
+            .expect_subject()
+            .with(
+                eq("Message:".to_string()),
+                eq("".to_string()),
+                always(),
+                always(),
+            )
+            .returning(|_, _, _, _| Ok("First commit".to_string()));
 
         prompt
             .expect_select()
@@ -169,10 +712,19 @@ mod test {
                 eq("Issue:".to_string()),
                 eq(vec!["#1".to_string(), "#2".to_string()]),
                 eq(false),
+                eq(None),
+                always(),
             )
-            .returning(|_, _, _| Ok("#2".to_string()));
+            .returning(|_, _, _, _, _| Ok("#2".to_string()));
 
-        let commit_message = run(&prompt, &git, commit_structure).unwrap();
+        let commit_message = run(
+            &prompt,
+            &git,
+            commit_structure,
+            &Draft::default(),
+            &Given::default(),
+        )
+        .unwrap();
 
         assert_eq!(commit_message, "First commit\n\nIssue: #2".to_string())
     }
@@ -182,20 +734,33 @@ mod test {
         let commit_structure = CommitStructure {
             trailers: vec![Trailer::Select(SelectTrailer {
                 name: "Issue".to_string(),
+                when: None,
+                validate: Validate::default(),
+                default: None,
                 values: Values::FromGitLog(ValuesFromGitLog {
                     max_count: None,
                     format_strings: vec!["%(trailers:key=Issue,valueonly=true)".to_string()],
+                    order: Order::Alpha,
+                    half_life: None,
+                    exclude_self: true,
                 }),
             })],
+            ..Default::default()
         };
 
         let mut prompt: MockPrompt = MockPrompt::new();
         let mut git: MockGit = MockGit::new();
+        git.expect_config_get_regexp().returning(|_| Ok(vec![]));
 
         prompt
-            .expect_text()
-            .with(eq("Message:".to_string()))
-            .returning(|_| Ok("First commit".to_string()));
+            .expect_subject()
+            .with(
+                eq("Message:".to_string()),
+                eq("".to_string()),
+                always(),
+                always(),
+            )
+            .returning(|_, _, _, _| Ok("First commit".to_string()));
 
         git.expect_log()
             .with(
@@ -210,10 +775,19 @@ mod test {
                 eq("Issue:".to_string()),
                 eq(vec!["#1".to_string(), "#2".to_string()]),
                 eq(true),
+                eq(None),
+                always(),
             )
-            .returning(|_, _, _| Ok("#2".to_string()));
+            .returning(|_, _, _, _, _| Ok("#2".to_string()));
 
-        let commit_message = run(&prompt, &git, commit_structure).unwrap();
+        let commit_message = run(
+            &prompt,
+            &git,
+            commit_structure,
+            &Draft::default(),
+            &Given::default(),
+        )
+        .unwrap();
 
         assert_eq!(commit_message, "First commit\n\nIssue: #2".to_string())
     }
@@ -223,20 +797,33 @@ mod test {
         let commit_structure = CommitStructure {
             trailers: vec![Trailer::Select(SelectTrailer {
                 name: "Issue".to_string(),
+                when: None,
+                validate: Validate::default(),
+                default: None,
                 values: Values::FromGitLog(ValuesFromGitLog {
                     max_count: None,
                     format_strings: vec!["%(trailers:key=Issue,valueonly=true)".to_string()],
+                    order: Order::Alpha,
+                    half_life: None,
+                    exclude_self: true,
                 }),
             })],
+            ..Default::default()
         };
 
         let mut prompt: MockPrompt = MockPrompt::new();
         let mut git: MockGit = MockGit::new();
+        git.expect_config_get_regexp().returning(|_| Ok(vec![]));
 
         prompt
-            .expect_text()
-            .with(eq("Message:".to_string()))
-            .returning(|_| Ok("First commit".to_string()));
+            .expect_subject()
+            .with(
+                eq("Message:".to_string()),
+                eq("".to_string()),
+                always(),
+                always(),
+            )
+            .returning(|_, _, _, _| Ok("First commit".to_string()));
 
         git.expect_log()
             .with(
@@ -247,10 +834,17 @@ mod test {
 
         prompt
             .expect_text()
-            .with(eq("Issue:".to_string()))
-            .returning(|_| Ok("#42".to_string()));
+            .with(eq("Issue:".to_string()), eq("".to_string()), always())
+            .returning(|_, _, _| Ok("#42".to_string()));
 
-        let commit_message = run(&prompt, &git, commit_structure).unwrap();
+        let commit_message = run(
+            &prompt,
+            &git,
+            commit_structure,
+            &Draft::default(),
+            &Given::default(),
+        )
+        .unwrap();
 
         assert_eq!(commit_message, "First commit\n\nIssue: #42".to_string())
     }
@@ -260,6 +854,9 @@ mod test {
         let commit_structure = CommitStructure {
             trailers: vec![Trailer::MultiSelect(MultiSelectTrailer {
                 name: "Co-authored-by".to_string(),
+                when: None,
+                validate: Validate::default(),
+                default: None,
                 values: Values::FromOptions(ValuesFromOptions {
                     options: vec![
                         "NAME <NAME@EXAMPLE.COM>".to_string(),
@@ -267,15 +864,22 @@ mod test {
                     ],
                 }),
             })],
+            ..Default::default()
         };
 
         let mut prompt: MockPrompt = MockPrompt::new();
-        let git: MockGit = MockGit::new();
+        let mut git: MockGit = MockGit::new();
+        git.expect_config_get_regexp().returning(|_| Ok(vec![]));
 
         prompt
-            .expect_text()
-            .with(eq("Message:".to_string()))
-            .returning(|_| Ok("First commit".to_string()));
+            .expect_subject()
+            .with(
+                eq("Message:".to_string()),
+                eq("".to_string()),
+                always(),
+                always(),
+            )
+            .returning(|_, _, _, _| Ok("First commit".to_string()));
 
         prompt
             .expect_multi_select()
@@ -286,10 +890,19 @@ mod test {
                     "ANOTHER-NAME <ANOTHER-NAME@EXAMPLE.COM>".to_string(),
                 ]),
                 eq(false),
+                eq(vec![]),
+                always(),
             )
-            .returning(|_, _, _| Ok(vec!["NAME <NAME@EXAMPLE.COM>".to_string()]));
+            .returning(|_, _, _, _, _| Ok(vec!["NAME <NAME@EXAMPLE.COM>".to_string()]));
 
-        let commit_message = run(&prompt, &git, commit_structure).unwrap();
+        let commit_message = run(
+            &prompt,
+            &git,
+            commit_structure,
+            &Draft::default(),
+            &Given::default(),
+        )
+        .unwrap();
 
         assert_eq!(
             commit_message,
@@ -302,22 +915,37 @@ mod test {
         let commit_structure = CommitStructure {
             trailers: vec![Trailer::MultiSelect(MultiSelectTrailer {
                 name: "Co-authored-by".to_string(),
+                when: None,
+                validate: Validate::default(),
+                default: None,
                 values: Values::FromGitLog(ValuesFromGitLog {
                     max_count: None,
                     format_strings: vec![
                         "%(trailers:key=Co-authored-by,valueonly=true)".to_string()
                     ],
+                    order: Order::Alpha,
+                    half_life: None,
+                    exclude_self: false,
                 }),
             })],
+            ..Default::default()
         };
 
         let mut prompt: MockPrompt = MockPrompt::new();
         let mut git: MockGit = MockGit::new();
+        git.expect_config_get_regexp().returning(|_| Ok(vec![]));
+        git.expect_check_mailmap()
+            .returning(|contacts| Ok(contacts.to_vec()));
 
         prompt
-            .expect_text()
-            .with(eq("Message:".to_string()))
-            .returning(|_| Ok("First commit".to_string()));
+            .expect_subject()
+            .with(
+                eq("Message:".to_string()),
+                eq("".to_string()),
+                always(),
+                always(),
+            )
+            .returning(|_, _, _, _| Ok("First commit".to_string()));
 
         git.expect_log()
             .with(
@@ -342,10 +970,19 @@ mod test {
                     "Joe Shmoe <joe.shmoe@example.org>".to_string(),
                 ]),
                 eq(true),
+                eq(vec![]),
+                always(),
             )
-            .returning(|_, _, _| Ok(vec!["Jane Doe <jane.doe@example.org>".to_string()]));
+            .returning(|_, _, _, _, _| Ok(vec!["Jane Doe <jane.doe@example.org>".to_string()]));
 
-        let commit_message = run(&prompt, &git, commit_structure).unwrap();
+        let commit_message = run(
+            &prompt,
+            &git,
+            commit_structure,
+            &Draft::default(),
+            &Given::default(),
+        )
+        .unwrap();
 
         assert_eq!(
             commit_message,
@@ -353,27 +990,155 @@ mod test {
         )
     }
 
+    fn co_authors_from_git_log(exclude_self: bool) -> CommitStructure {
+        CommitStructure {
+            trailers: vec![Trailer::MultiSelect(MultiSelectTrailer {
+                name: "Co-authored-by".to_string(),
+                when: None,
+                validate: Validate::default(),
+                default: None,
+                values: Values::FromGitLog(ValuesFromGitLog {
+                    max_count: None,
+                    format_strings: vec!["%an <%ae>".to_string()],
+                    order: Order::Alpha,
+                    half_life: None,
+                    exclude_self,
+                }),
+            })],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_multi_select_from_git_log_merges_identities_with_the_mailmap() {
+        let mut prompt: MockPrompt = MockPrompt::new();
+        let mut git: MockGit = MockGit::new();
+        git.expect_config_get_regexp().returning(|_| Ok(vec![]));
+        prompt
+            .expect_subject()
+            .returning(|_, _, _, _| Ok("First commit".to_string()));
+        git.expect_log().returning(|_, _| {
+            Ok(vec![
+                "J. Doe <jd@old.example.org>".to_string(),
+                "James Smith <james.smith@example.org>".to_string(),
+                "Jane Doe <jane.doe@example.org>".to_string(),
+                "jane doe <Jane.Doe@example.org>".to_string(),
+            ])
+        });
+        git.expect_check_mailmap().returning(|contacts| {
+            Ok(contacts
+                .iter()
+                .map(|c| {
+                    c.replace(
+                        "J. Doe <jd@old.example.org>",
+                        "Jane Doe <jane.doe@example.org>",
+                    )
+                })
+                .collect())
+        });
+
+        prompt
+            .expect_multi_select()
+            .with(
+                eq("Co-authored-by:".to_string()),
+                eq(vec![
+                    "James Smith <james.smith@example.org>".to_string(),
+                    "Jane Doe <jane.doe@example.org>".to_string(),
+                ]),
+                eq(true),
+                eq(vec![]),
+                always(),
+            )
+            .returning(|_, _, _, _, _| Ok(vec![]));
+
+        run(
+            &prompt,
+            &git,
+            co_authors_from_git_log(false),
+            &Draft::default(),
+            &Given::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_multi_select_from_git_log_excludes_the_committer() {
+        let mut prompt: MockPrompt = MockPrompt::new();
+        let mut git: MockGit = MockGit::new();
+        git.expect_config_get_regexp().returning(|_| Ok(vec![]));
+        prompt
+            .expect_subject()
+            .returning(|_, _, _, _| Ok("First commit".to_string()));
+        git.expect_log().returning(|_, _| {
+            Ok(vec![
+                "James Smith <james.smith@example.org>".to_string(),
+                "Joe Shmoe <joe.shmoe@example.org>".to_string(),
+            ])
+        });
+        git.expect_config_get()
+            .with(eq("user.name"))
+            .returning(|_| Ok(Some("Joe Shmoe".to_string())));
+        git.expect_config_get()
+            .with(eq("user.email"))
+            .returning(|_| Ok(Some("Joe.Shmoe@example.org".to_string())));
+        git.expect_check_mailmap()
+            .returning(|contacts| Ok(contacts.to_vec()));
+
+        prompt
+            .expect_multi_select()
+            .with(
+                eq("Co-authored-by:".to_string()),
+                eq(vec!["James Smith <james.smith@example.org>".to_string()]),
+                eq(true),
+                eq(vec![]),
+                always(),
+            )
+            .returning(|_, _, _, _, _| Ok(vec![]));
+
+        run(
+            &prompt,
+            &git,
+            co_authors_from_git_log(true),
+            &Draft::default(),
+            &Given::default(),
+        )
+        .unwrap();
+    }
+
     #[test]
     fn test_multi_select_from_git_log_when_it_is_empty() {
         let commit_structure = CommitStructure {
             trailers: vec![Trailer::MultiSelect(MultiSelectTrailer {
                 name: "Co-authored-by".to_string(),
+                when: None,
+                validate: Validate::default(),
+                default: None,
                 values: Values::FromGitLog(ValuesFromGitLog {
                     max_count: None,
                     format_strings: vec![
                         "%(trailers:key=Co-authored-by,valueonly=true)".to_string()
                     ],
+                    order: Order::Alpha,
+                    half_life: None,
+                    exclude_self: true,
                 }),
             })],
+            ..Default::default()
         };
 
         let mut prompt: MockPrompt = MockPrompt::new();
         let mut git: MockGit = MockGit::new();
+        git.expect_config_get_regexp().returning(|_| Ok(vec![]));
 
         prompt
-            .expect_text()
-            .with(eq("Message:".to_string()))
-            .returning(|_| Ok("First commit".to_string()));
+            .expect_subject()
+            .with(
+                eq("Message:".to_string()),
+                eq("".to_string()),
+                always(),
+                always(),
+            )
+            .returning(|_, _, _, _| Ok("First commit".to_string()));
 
         git.expect_log()
             .with(
@@ -384,14 +1149,421 @@ mod test {
 
         prompt
             .expect_multi_text()
-            .with(eq("Co-authored-by:".to_string()))
-            .returning(|_| Ok(vec!["Jane Doe <jane.doe@example.org>".to_string()]));
+            .with(eq("Co-authored-by:".to_string()), always())
+            .returning(|_, _| Ok(vec!["Jane Doe <jane.doe@example.org>".to_string()]));
 
-        let commit_message = run(&prompt, &git, commit_structure).unwrap();
+        let commit_message = run(
+            &prompt,
+            &git,
+            commit_structure,
+            &Draft::default(),
+            &Given::default(),
+        )
+        .unwrap();
 
         assert_eq!(
             commit_message,
             "First commit\n\nCo-authored-by: Jane Doe <jane.doe@example.org>".to_string()
         )
     }
+
+    #[test]
+    fn test_draft_is_pre_filled() {
+        let commit_structure = CommitStructure {
+            trailers: vec![Trailer::MultiSelect(MultiSelectTrailer {
+                name: "Co-authored-by".to_string(),
+                when: None,
+                validate: Validate::default(),
+                default: None,
+                values: Values::FromOptions(ValuesFromOptions {
+                    options: vec![
+                        "James Smith <james.smith@example.org>".to_string(),
+                        "Jane Doe <jane.doe@example.org>".to_string(),
+                    ],
+                }),
+            })],
+            ..Default::default()
+        };
+        let draft = Draft::parse(
+            "First commit\n\nSome details\n\nco-authored-by: Jane Doe <jane.doe@example.org>\nSigned-off-by: Joe Shmoe <joe.shmoe@example.org>\n",
+            &Config::default(),
+        );
+
+        let mut prompt: MockPrompt = MockPrompt::new();
+        let mut git: MockGit = MockGit::new();
+        git.expect_config_get_regexp().returning(|_| Ok(vec![]));
+
+        prompt
+            .expect_subject()
+            .with(
+                eq("Message:".to_string()),
+                eq("First commit".to_string()),
+                always(),
+                always(),
+            )
+            .returning(|_, _, _, _| Ok("First commit, amended".to_string()));
+
+        prompt
+            .expect_multi_select()
+            .with(
+                eq("Co-authored-by:".to_string()),
+                eq(vec![
+                    "James Smith <james.smith@example.org>".to_string(),
+                    "Jane Doe <jane.doe@example.org>".to_string(),
+                ]),
+                eq(false),
+                eq(vec![1]),
+                always(),
+            )
+            .returning(|_, _, _, _, _| {
+                Ok(vec![
+                    "James Smith <james.smith@example.org>".to_string(),
+                    "Jane Doe <jane.doe@example.org>".to_string(),
+                ])
+            });
+
+        let commit_message =
+            run(&prompt, &git, commit_structure, &draft, &Given::default()).unwrap();
+
+        assert_eq!(
+            commit_message,
+            "First commit, amended\n\nSome details\n\nCo-authored-by: James Smith <james.smith@example.org>\nCo-authored-by: Jane Doe <jane.doe@example.org>\nSigned-off-by: Joe Shmoe <joe.shmoe@example.org>".to_string()
+        )
+    }
+
+    #[test]
+    fn test_defaults() {
+        let commit_structure = CommitStructure {
+            trailers: vec![
+                Trailer::Text(TextTrailer {
+                    name: "Refs".to_string(),
+                    when: None,
+                    validate: Validate::default(),
+                    default: Some(DefaultValue::Dynamic(DynamicDefault {
+                        from: DefaultFrom::Branch,
+                        pattern: Some(r"^\w+/([A-Z]+-\d+)".to_string()),
+                    })),
+                }),
+                Trailer::Select(SelectTrailer {
+                    name: "Type".to_string(),
+                    when: None,
+                    validate: Validate::default(),
+                    default: Some(DefaultValue::Dynamic(DynamicDefault {
+                        from: DefaultFrom::LastCommit,
+                        pattern: None,
+                    })),
+                    values: Values::FromOptions(ValuesFromOptions {
+                        options: vec!["feature".to_string(), "fix".to_string()],
+                    }),
+                }),
+                Trailer::MultiSelect(MultiSelectTrailer {
+                    name: "Reviewed-by".to_string(),
+                    when: None,
+                    validate: Validate::default(),
+                    default: Some(DefaultValue::Values(vec![
+                        "Jane Doe <jane.doe@example.org>".to_string(),
+                    ])),
+                    values: Values::FromOptions(ValuesFromOptions {
+                        options: vec![
+                            "James Smith <james.smith@example.org>".to_string(),
+                            "Jane Doe <jane.doe@example.org>".to_string(),
+                        ],
+                    }),
+                }),
+            ],
+            ..Default::default()
+        };
+
+        let mut prompt: MockPrompt = MockPrompt::new();
+        let mut git: MockGit = MockGit::new();
+        git.expect_config_get_regexp().returning(|_| Ok(vec![]));
+
+        git.expect_current_branch()
+            .returning(|| Ok(Some("feature/PROJ-42-defaults".to_string())));
+        git.expect_message()
+            .with(eq("HEAD"))
+            .returning(|_| Ok("Previous commit\n\nType: fix\n".to_string()));
+
+        prompt
+            .expect_subject()
+            .with(
+                eq("Message:".to_string()),
+                eq("".to_string()),
+                always(),
+                always(),
+            )
+            .returning(|_, _, _, _| Ok("First commit".to_string()));
+        prompt
+            .expect_text()
+            .with(eq("Refs:".to_string()), eq("PROJ-42".to_string()), always())
+            .returning(|_, initial, _| Ok(initial.to_string()));
+        prompt
+            .expect_select()
+            .with(
+                eq("Type:".to_string()),
+                eq(vec!["feature".to_string(), "fix".to_string()]),
+                eq(false),
+                eq(Some(1)),
+                always(),
+            )
+            .returning(|_, _, _, _, _| Ok("fix".to_string()));
+        prompt
+            .expect_multi_select()
+            .with(
+                eq("Reviewed-by:".to_string()),
+                always(),
+                eq(false),
+                eq(vec![1]),
+                always(),
+            )
+            .returning(|_, _, _, _, _| Ok(vec!["Jane Doe <jane.doe@example.org>".to_string()]));
+
+        let commit_message = run(
+            &prompt,
+            &git,
+            commit_structure,
+            &Draft::default(),
+            &Given::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            commit_message,
+            "First commit\n\nRefs: PROJ-42\nType: fix\nReviewed-by: Jane Doe <jane.doe@example.org>"
+        )
+    }
+
+    #[test]
+    fn test_when() {
+        let commit_structure = CommitStructure {
+            trailers: vec![
+                Trailer::Select(SelectTrailer {
+                    name: "Breaking-change".to_string(),
+                    when: None,
+                    validate: Validate::default(),
+                    default: None,
+                    values: Values::FromOptions(ValuesFromOptions {
+                        options: vec!["yes".to_string(), "no".to_string()],
+                    }),
+                }),
+                Trailer::Text(TextTrailer {
+                    name: "Breaking-change-note".to_string(),
+                    when: Some(When {
+                        answers: BTreeMap::from([(
+                            "Breaking-change".to_string(),
+                            "yes".to_string(),
+                        )]),
+                        ..Default::default()
+                    }),
+                    validate: Validate::default(),
+                    default: None,
+                }),
+                Trailer::Text(TextTrailer {
+                    name: "Issue".to_string(),
+                    when: Some(When {
+                        branch: vec!["feature/*".to_string()],
+                        ..Default::default()
+                    }),
+                    validate: Validate::default(),
+                    default: None,
+                }),
+            ],
+            ..Default::default()
+        };
+
+        let mut prompt: MockPrompt = MockPrompt::new();
+        let mut git: MockGit = MockGit::new();
+        git.expect_config_get_regexp().returning(|_| Ok(vec![]));
+
+        git.expect_current_branch()
+            .returning(|| Ok(Some("main".to_string())));
+        prompt
+            .expect_subject()
+            .with(
+                eq("Message:".to_string()),
+                eq("First commit".to_string()),
+                always(),
+                always(),
+            )
+            .returning(|_, _, _, _| Ok("First commit".to_string()));
+        prompt
+            .expect_select()
+            .returning(|_, _, _, _, _| Ok("no".to_string()));
+
+        let draft = Draft::parse("First commit\n\nIssue: #42\n", &Config::default());
+        let commit_message =
+            run(&prompt, &git, commit_structure, &draft, &Given::default()).unwrap();
+
+        assert_eq!(
+            commit_message,
+            "First commit\n\nBreaking-change: no\nIssue: #42"
+        )
+    }
+
+    #[test]
+    fn test_given() {
+        let commit_structure = || CommitStructure {
+            trailers: vec![
+                Trailer::Select(SelectTrailer {
+                    name: "Issue".to_string(),
+                    when: None,
+                    validate: Validate {
+                        required: true,
+                        ..Default::default()
+                    },
+                    default: None,
+                    values: Values::FromOptions(ValuesFromOptions {
+                        options: vec!["ABC-1".to_string(), "ABC-2".to_string()],
+                    }),
+                }),
+                Trailer::MultiSelect(MultiSelectTrailer {
+                    name: "Co-authored-by".to_string(),
+                    when: None,
+                    validate: Validate::default(),
+                    default: None,
+                    values: Values::FromAuthors(ValuesFromAuthors {}),
+                }),
+            ],
+            authors: BTreeMap::from([(
+                "jd".to_string(),
+                "Jane Doe <jane.doe@example.org>".to_string(),
+            )]),
+            ..Default::default()
+        };
+        let given = |issue: &str, no_interactive: bool| Given {
+            message: Some("Fix the build".to_string()),
+            trailers: vec![
+                ("Reviewed-by".to_string(), "jd".to_string()),
+                ("issue".to_string(), issue.to_string()),
+                ("Acked-by".to_string(), "jd".to_string()),
+            ],
+            no_interactive,
+        };
+
+        let mut prompt: MockPrompt = MockPrompt::new();
+        let mut git: MockGit = MockGit::new();
+        git.expect_config_get_regexp().returning(|_| Ok(vec![]));
+        prompt
+            .expect_multi_select()
+            .with(
+                eq("Co-authored-by:".to_string()),
+                always(),
+                eq(true),
+                eq(vec![]),
+                always(),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(vec!["jd".to_string()]));
+
+        let commit_message = run(
+            &prompt,
+            &git,
+            commit_structure(),
+            &Draft::default(),
+            &given("ABC-1", false),
+        )
+        .unwrap();
+        assert_eq!(
+            commit_message,
+            "Fix the build\n\nIssue: ABC-1\nCo-authored-by: Jane Doe <jane.doe@example.org>\nReviewed-by: jd\nAcked-by: jd"
+        );
+
+        let commit_message = run(
+            &prompt,
+            &git,
+            commit_structure(),
+            &Draft::default(),
+            &given("ABC-2", true),
+        )
+        .unwrap();
+        assert_eq!(
+            commit_message,
+            "Fix the build\n\nIssue: ABC-2\nReviewed-by: jd\nAcked-by: jd"
+        );
+
+        let error = run(
+            &prompt,
+            &git,
+            commit_structure(),
+            &Draft::default(),
+            &given("ABC-3", true),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid answer: \"Issue: ABC-3\" is not one of the allowed values: ABC-1, ABC-2"
+        );
+
+        let error = run(
+            &prompt,
+            &git,
+            commit_structure(),
+            &Draft::default(),
+            &Given {
+                no_interactive: true,
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "A message is required, give it with -m");
+    }
+
+    fn entries() -> Vec<LogEntry> {
+        let entry = |timestamp: u64, value: &str| LogEntry {
+            timestamp,
+            value: value.to_string(),
+        };
+        vec![
+            entry(900_000, "Jane Doe <jane.doe@example.org>"),
+            entry(800_000, "Joe Shmoe <joe.shmoe@example.org>"),
+            entry(200_000, "Joe Shmoe <joe.shmoe@example.org>"),
+            entry(100_000, "Joe Shmoe <joe.shmoe@example.org>"),
+        ]
+    }
+
+    fn values(order: Order, half_life: Option<u64>) -> Vec<String> {
+        rank(&entries(), order, half_life, 1_000_000)
+            .into_iter()
+            .map(|r| r.value)
+            .collect()
+    }
+
+    #[test]
+    fn test_rank() {
+        assert_eq!(
+            values(Order::Recent, None),
+            vec![
+                "Jane Doe <jane.doe@example.org>".to_string(),
+                "Joe Shmoe <joe.shmoe@example.org>".to_string(),
+            ]
+        );
+        assert_eq!(
+            values(Order::Frequent, None),
+            vec![
+                "Joe Shmoe <joe.shmoe@example.org>".to_string(),
+                "Jane Doe <jane.doe@example.org>".to_string(),
+            ]
+        );
+        assert_eq!(
+            values(Order::Frequent, Some(60_000)),
+            vec![
+                "Jane Doe <jane.doe@example.org>".to_string(),
+                "Joe Shmoe <joe.shmoe@example.org>".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rank_label() {
+        let ranked = rank(&entries(), Order::Frequent, None, 1_000_000);
+
+        assert_eq!(
+            ranked[0].label(1_000_000),
+            "Joe Shmoe <joe.shmoe@example.org> — 3 commits, last 2d ago"
+        );
+        assert_eq!(
+            ranked[1].label(1_000_000),
+            "Jane Doe <jane.doe@example.org> — 1 commit, last 1d ago"
+        );
+    }
 }
@@ -0,0 +1,209 @@
+use crate::check::check_value;
+use crate::git::Git;
+use crate::prompt::Prompt;
+use crate::run;
+use crate::structure::{CommitStructure, Trailer, Validate};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SESSION_FILE: &str = "git-bottle-session.yml";
+
+/// Trailers shared by every commit of a pair/mob session, stored in the git directory.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// Unix timestamp after which the session is ignored
+    pub expires_at: u64,
+    pub trailers: Vec<(String, String)>,
+}
+
+impl Session {
+    /// Adds a value to a trailer, once checked against its rules.
+    pub fn add(
+        &mut self,
+        name: &str,
+        value: &str,
+        validate: &Validate,
+    ) -> Result<(), anyhow::Error> {
+        if let Some(violation) = check_value(name, validate, value)?.first() {
+            return Err(anyhow!("Invalid value: {}", violation));
+        }
+        let exists = self
+            .trailers
+            .iter()
+            .any(|(k, v)| k.eq_ignore_ascii_case(name) && v == value);
+        if !exists {
+            self.trailers.push((name.to_string(), value.to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str, value: &str) -> bool {
+        let len = self.trailers.len();
+        self.trailers
+            .retain(|(k, v)| !(k.eq_ignore_ascii_case(name) && v == value));
+        self.trailers.len() != len
+    }
+}
+
+/// Prompts for the multi-selection trailers of the configuration, pre-selecting the ones of the
+/// current session.
+pub fn start(
+    prompt: &impl Prompt,
+    git: &impl Git,
+    mut commit_structure: CommitStructure,
+    current: Option<Session>,
+    expires_at: u64,
+) -> Result<Session, anyhow::Error> {
+    commit_structure
+        .trailers
+        .retain(|t| matches!(t, Trailer::MultiSelect(_)));
+    if commit_structure.trailers.is_empty() {
+        return Err(anyhow!(
+            "A session needs at least one multi_select trailer in the configuration"
+        ));
+    }
+
+    let existing = current.map(|s| s.trailers).unwrap_or_default();
+    let trailers = run::trailers(
+        prompt,
+        git,
+        commit_structure,
+        &existing,
+        &run::Given::default(),
+    )?;
+    Ok(Session {
+        expires_at,
+        trailers,
+    })
+}
+
+/// Loads the current session, removing it when expired.
+pub fn load(git: &impl Git, now: u64) -> Result<Option<Session>, anyhow::Error> {
+    let path = path(git)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| anyhow!("Could not read file {}: {}", path.display(), e))?;
+    let session: Session = serde_yaml::from_str(&content)
+        .map_err(|e| anyhow!("Malformed session file {}: {}", path.display(), e))?;
+    if session.expires_at <= now {
+        end(git)?;
+        return Ok(None);
+    }
+    Ok(Some(session))
+}
+
+pub fn save(git: &impl Git, session: &Session) -> Result<(), anyhow::Error> {
+    let path = path(git)?;
+    let content = serde_yaml::to_string(session).map_err(|e| anyhow!(e))?;
+    fs::write(&path, content).map_err(|e| anyhow!("Could not write file {}: {}", path.display(), e))
+}
+
+pub fn end(git: &impl Git) -> Result<bool, anyhow::Error> {
+    let path = path(git)?;
+    if !path.exists() {
+        return Ok(false);
+    }
+    fs::remove_file(&path)
+        .map_err(|e| anyhow!("Could not remove file {}: {}", path.display(), e))?;
+    Ok(true)
+}
+
+fn path(git: &impl Git) -> Result<PathBuf, anyhow::Error> {
+    git.git_path(SESSION_FILE)
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Parses a duration like `90m`, `8h` or `2d` into seconds.
+pub fn parse_duration(value: &str) -> Result<u64, anyhow::Error> {
+    let invalid = || anyhow!("Invalid duration {}, use e.g. 90m, 8h or 2d", value);
+    let value = value.trim();
+    let unit_start = value.char_indices().last().map_or(0, |(i, _)| i);
+    let (amount, unit) = value.split_at(unit_start);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let unit: u64 = match unit {
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => return Err(invalid()),
+    };
+    amount.checked_mul(unit).ok_or_else(invalid)
+}
+
+/// Formats a duration in seconds like `1d 2h`, `3h 20m` or `5m`.
+pub fn format_duration(seconds: u64) -> String {
+    let (days, hours, minutes) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::session::{format_duration, parse_duration, Session};
+    use crate::structure::Validate;
+
+    #[test]
+    fn test_add_and_remove() {
+        let mut session = Session {
+            expires_at: 0,
+            trailers: vec![],
+        };
+
+        let validate = Validate::default();
+        for (name, value) in [
+            ("Co-authored-by", "Jane Doe <jane.doe@example.org>"),
+            ("co-authored-by", "Jane Doe <jane.doe@example.org>"),
+            ("Co-authored-by", "Joe Shmoe <joe.shmoe@example.org>"),
+        ] {
+            session.add(name, value, &validate).unwrap();
+        }
+        assert_eq!(
+            session
+                .add("Co-authored-by", "bob", &validate)
+                .unwrap_err()
+                .to_string(),
+            "Invalid value: \"Co-authored-by: bob\" is not in the format \"Name <email>\""
+        );
+        assert!(session.remove("Co-authored-by", "Jane Doe <jane.doe@example.org>"));
+        assert!(!session.remove("Co-authored-by", "Jane Doe <jane.doe@example.org>"));
+
+        assert_eq!(
+            session.trailers,
+            vec![(
+                "Co-authored-by".to_string(),
+                "Joe Shmoe <joe.shmoe@example.org>".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90m").unwrap(), 5400);
+        assert_eq!(parse_duration("8h").unwrap(), 28800);
+        assert_eq!(parse_duration("2d").unwrap(), 172800);
+        assert!(parse_duration("8").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("8é").is_err());
+        assert!(parse_duration("99999999999999999d").is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(300), "5m");
+        assert_eq!(format_duration(12000), "3h 20m");
+        assert_eq!(format_duration(93600), "1d 2h");
+    }
+}
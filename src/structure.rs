@@ -1,54 +1,375 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// The configuration of git-bottle, `.git-bottle.yml`
 pub struct CommitStructure {
+    /// The version of the configuration format, 1 when missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    /// The commit trailers to prompt for
+    #[serde(default)]
     pub trailers: Vec<Trailer>,
+    /// How the trailers are merged with the ones of the previous configuration layers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trailers_merge: Option<TrailersMerge>,
+    /// Team roster mapping short handles (e.g. initials) to `Name <email>` identities
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub authors: BTreeMap<String, String>,
+    /// Canonical `Name <email>` identities with their other names or emails
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub identities: BTreeMap<String, Vec<String>>,
+    /// How the commit message is prompted
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub message: Message,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Message {
+    /// The style of the message
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub style: MessageStyle,
+    /// The types of a conventional commit, the usual ones when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<CommitType>,
+    /// The options for the scope of a conventional commit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Scopes>,
+    /// The maximum length of the subject line, 72 when missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject_max_length: Option<usize>,
+    /// Whether to prompt for a body in the editor of git, always the case for a conventional commit
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub body: bool,
+    /// The width the body is wrapped at, 72 when missing, 0 to keep it as it is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wrap: Option<usize>,
+    /// The template the message is rendered from, e.g. `{type}: {summary}\n\n{body}\n\nRefs: {Issue}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// The pattern of the ticket IDs of the `{ticket}` placeholder, found in the branch name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ticket_pattern: Option<String>,
+}
+
+impl Message {
+    pub fn subject_max_length(&self) -> usize {
+        self.subject_max_length.unwrap_or(DEFAULT_LINE_LENGTH)
+    }
+
+    pub fn wrap(&self) -> usize {
+        self.wrap.unwrap_or(DEFAULT_LINE_LENGTH)
+    }
+
+    pub fn ticket_pattern(&self) -> &str {
+        self.ticket_pattern
+            .as_deref()
+            .unwrap_or(DEFAULT_TICKET_PATTERN)
+    }
+}
+
+/// The usual maximum length of the lines of a commit message.
+const DEFAULT_LINE_LENGTH: usize = 72;
+
+/// The usual ticket IDs, e.g. `ABC-123`.
+const DEFAULT_TICKET_PATTERN: &str = "[A-Z][A-Z0-9]+-[0-9]+";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageStyle {
+    /// A free text message
+    #[default]
+    Free,
+    /// A Conventional Commits message, `type(scope)!: description`
+    Conventional,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CommitType {
+    /// The type, e.g. `feat`
+    pub name: String,
+    /// What the type is for, e.g. `A new feature`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type")]
+#[allow(clippy::enum_variant_names)]
+pub enum Scopes {
+    /// An explicit list of scopes
+    #[serde(rename = "from_options")]
+    FromOptions(ValuesFromOptions),
+    /// The scopes of the subjects in the git log
+    #[serde(rename = "from_git_log")]
+    FromGitLog(ScopesFromGitLog),
+    /// The top-level directories of the staged files
+    #[serde(rename = "from_staged_paths")]
+    FromStagedPaths(ScopesFromStagedPaths),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ScopesFromGitLog {
+    /// Limit the number of commits in the git log
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_count: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ScopesFromStagedPaths {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailersMerge {
+    /// Replace the previous trailers
+    Replace,
+    /// Add the trailers after the previous ones
+    Append,
+    /// Replace the previous trailers with the same name, add the others
+    Override,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum Trailer {
+    /// A free text value
     #[serde(rename = "text")]
     Text(TextTrailer),
+    /// A single value selected among options
     #[serde(rename = "select")]
     Select(SelectTrailer),
+    /// Any number of values selected among options
     #[serde(rename = "multi_select")]
     MultiSelect(MultiSelectTrailer),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Trailer {
+    pub fn name(&self) -> &str {
+        match self {
+            Trailer::Text(t) => &t.name,
+            Trailer::Select(t) => &t.name,
+            Trailer::MultiSelect(t) => &t.name,
+        }
+    }
+
+    pub fn when(&self) -> Option<&When> {
+        match self {
+            Trailer::Text(t) => t.when.as_ref(),
+            Trailer::Select(t) => t.when.as_ref(),
+            Trailer::MultiSelect(t) => t.when.as_ref(),
+        }
+    }
+
+    pub fn default(&self) -> Option<&DefaultValue> {
+        match self {
+            Trailer::Text(t) => t.default.as_ref(),
+            Trailer::Select(t) => t.default.as_ref(),
+            Trailer::MultiSelect(t) => t.default.as_ref(),
+        }
+    }
+
+    pub fn validate(&self) -> &Validate {
+        match self {
+            Trailer::Text(t) => &t.validate,
+            Trailer::Select(t) => &t.validate,
+            Trailer::MultiSelect(t) => &t.validate,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct TextTrailer {
+    /// The name of the trailer
     pub name: String,
+    /// Conditions for prompting the trailer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<When>,
+    /// Rules for the values of the trailer
+    #[serde(default, skip_serializing_if = "Validate::is_empty")]
+    pub validate: Validate,
+    /// The value offered before any answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<DefaultValue>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct SelectTrailer {
+    /// The name of the trailer
     pub name: String,
+    /// Conditions for prompting the trailer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<When>,
+    /// Rules for the values of the trailer
+    #[serde(default, skip_serializing_if = "Validate::is_empty")]
+    pub validate: Validate,
+    /// The value offered before any answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<DefaultValue>,
+    /// The options for selection
     pub values: Values,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct MultiSelectTrailer {
+    /// The name of the trailer
     pub name: String,
+    /// Conditions for prompting the trailer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<When>,
+    /// Rules for the values of the trailer
+    #[serde(default, skip_serializing_if = "Validate::is_empty")]
+    pub validate: Validate,
+    /// The value offered before any answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<DefaultValue>,
+    /// The options for selection
     pub values: Values,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct When {
+    /// Glob patterns, one of which the current branch must match, e.g. `feature/*`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub branch: Vec<String>,
+    /// Glob patterns, one of which a staged file must match, e.g. `src/**`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Glob patterns, by trailer name, one of the earlier answers to the trailer must match
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub answers: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum DefaultValue {
+    /// A literal value
+    Value(String),
+    /// Literal values, pre-selected by a `multi_select` trailer
+    Values(Vec<String>),
+    /// A value computed before prompting
+    Dynamic(DynamicDefault),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DynamicDefault {
+    /// Where the value comes from
+    pub from: DefaultFrom,
+    /// Regular expression extracting the value, its first group if any, e.g. `[A-Z]+-\d+`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultFrom {
+    /// The values of the trailer in the last commit
+    LastCommit,
+    /// The name of the current branch
+    Branch,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Validate {
+    /// Whether the trailer must have a value
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub required: bool,
+    /// Regular expression that every value of the trailer must match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Whether every value of the trailer must be a `Name <email>` identity
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub identity: bool,
+    /// The maximum number of values of the trailer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_count: Option<usize>,
+    /// The maximum length of every value of the trailer, in characters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+}
+
+impl Validate {
+    pub fn is_empty(&self) -> bool {
+        *self == Validate::default()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
+#[allow(clippy::enum_variant_names)]
 pub enum Values {
+    /// An explicit list of options
     #[serde(rename = "from_options")]
     FromOptions(ValuesFromOptions),
+    /// The values found in the git log
     #[serde(rename = "from_git_log")]
     FromGitLog(ValuesFromGitLog),
+    /// The identities of the `authors` roster
+    #[serde(rename = "from_authors")]
+    FromAuthors(ValuesFromAuthors),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ValuesFromOptions {
+    /// The options for selection
     pub options: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ValuesFromGitLog {
+    /// Limit the number of commits in the git log
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_count: Option<i32>,
+    /// Format strings extracting the values from the git log (`git log --format=<format-string>`)
     pub format_strings: Vec<String>,
+    /// The order of the options
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub order: Order,
+    /// Half-life of an occurrence for the `frequent` order, e.g. `30d`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub half_life: Option<String>,
+    /// Whether to leave out the identity of the committer, `user.name <user.email>`
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub exclude_self: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
 }
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Order {
+    /// Alphabetical
+    #[default]
+    Alpha,
+    /// The most recently used first
+    Recent,
+    /// The most used first
+    Frequent,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ValuesFromAuthors {}
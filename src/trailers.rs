@@ -0,0 +1,621 @@
+use crate::conventional::BREAKING_CHANGE;
+use crate::git::Git;
+use anyhow::anyhow;
+
+pub const SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+/// The lines git recognizes as trailers in a block which also has other lines.
+const GIT_GENERATED_PREFIXES: [&str; 2] = ["Signed-off-by: ", "(cherry picked from commit "];
+
+/// Parses the trailers of a commit message the way `git interpret-trailers` finds them, ignoring
+/// comments.
+pub fn parse(message: &str, config: &Config) -> Vec<(String, String)> {
+    split(message, config).1
+}
+
+/// Splits a commit message, ignoring comments, into the text before the trailers and the trailers.
+pub fn split(message: &str, config: &Config) -> (String, Vec<(String, String)>) {
+    let message = match message.find(SCISSORS) {
+        Some(index) => &message[..index],
+        None => message,
+    };
+    let lines: Vec<&str> = message.lines().filter(|l| !l.starts_with('#')).collect();
+    let (text, trailers) = split_block(&lines.join("\n"), config);
+    (text.trim().to_string(), trailers)
+}
+
+/// Where a trailer is added, relative to the trailers with the same key, `trailer.where`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Where {
+    #[default]
+    End,
+    Start,
+    After,
+    Before,
+}
+
+/// What to do when a trailer with the same key exists, `trailer.ifExists`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum IfExists {
+    #[default]
+    AddIfDifferentNeighbor,
+    AddIfDifferent,
+    Add,
+    Replace,
+    DoNothing,
+}
+
+/// What to do when no trailer with the same key exists, `trailer.ifMissing`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum IfMissing {
+    #[default]
+    Add,
+    DoNothing,
+}
+
+/// The `trailer.<token>.*` git configuration of a key.
+#[derive(Debug, Default, PartialEq)]
+struct Token {
+    name: String,
+    key: Option<String>,
+    place: Option<Where>,
+    if_exists: Option<IfExists>,
+    if_missing: Option<IfMissing>,
+}
+
+/// The `trailer.*` git configuration, applied the way `git interpret-trailers` does.
+#[derive(Debug, PartialEq)]
+pub struct Config {
+    separators: String,
+    place: Where,
+    if_exists: IfExists,
+    if_missing: IfMissing,
+    tokens: Vec<Token>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            separators: ":".to_string(),
+            place: Where::default(),
+            if_exists: IfExists::default(),
+            if_missing: IfMissing::default(),
+            tokens: vec![],
+        }
+    }
+}
+
+impl Config {
+    pub fn read(git: &impl Git) -> Result<Config, anyhow::Error> {
+        Config::from_entries(&git.config_get_regexp(r"^trailer\.")?)
+    }
+
+    fn from_entries(entries: &[(String, String)]) -> Result<Config, anyhow::Error> {
+        let mut config = Config::default();
+        for (key, value) in entries {
+            let Some(name) = key.strip_prefix("trailer.") else {
+                continue;
+            };
+            // git lowercases the section and variable names, but not the tokens
+            match name.rsplit_once('.') {
+                None => match name.to_ascii_lowercase().as_str() {
+                    "separators" if !value.is_empty() => config.separators = value.clone(),
+                    "where" => config.place = parse_where(key, value)?,
+                    "ifexists" => config.if_exists = parse_if_exists(key, value)?,
+                    "ifmissing" => config.if_missing = parse_if_missing(key, value)?,
+                    _ => {}
+                },
+                Some((token, variable)) => {
+                    let index = match config.tokens.iter().position(|t| t.name == token) {
+                        Some(index) => index,
+                        None => {
+                            config.tokens.push(Token {
+                                name: token.to_string(),
+                                ..Default::default()
+                            });
+                            config.tokens.len() - 1
+                        }
+                    };
+                    let token = &mut config.tokens[index];
+                    match variable.to_ascii_lowercase().as_str() {
+                        "key" => token.key = Some(value.clone()),
+                        "where" => token.place = Some(parse_where(key, value)?),
+                        "ifexists" => token.if_exists = Some(parse_if_exists(key, value)?),
+                        "ifmissing" => token.if_missing = Some(parse_if_missing(key, value)?),
+                        // commands are not run
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// The configuration of the key, matching its name or its `key`.
+    fn token(&self, key: &str) -> Option<&Token> {
+        let key = self.without_separator(key);
+        self.tokens.iter().find(|t| {
+            t.name.eq_ignore_ascii_case(key)
+                || t.key
+                    .as_deref()
+                    .is_some_and(|k| self.without_separator(k).eq_ignore_ascii_case(key))
+        })
+    }
+
+    /// The key as it is written, e.g. `Bug #` for `bug` when `trailer.bug.key` is `Bug #`.
+    fn key(&self, key: &str) -> String {
+        match self.token(key).and_then(|t| t.key.as_ref()) {
+            Some(configured) => configured.clone(),
+            None => key.to_string(),
+        }
+    }
+
+    fn without_separator<'a>(&self, key: &'a str) -> &'a str {
+        key.trim_end()
+            .trim_end_matches(|c| self.separators.contains(c))
+            .trim_end()
+    }
+
+    fn format(&self, key: &str, value: &str) -> String {
+        match key.trim_end().chars().last() {
+            Some(c) if self.separators.contains(c) => format!("{}{}", key, value),
+            _ => format!(
+                "{}{} {}",
+                key,
+                self.separators.chars().next().unwrap_or(':'),
+                value
+            ),
+        }
+    }
+
+    /// The position of the separator of a trailer line, after a key of letters, digits and `-`.
+    fn separator(&self, line: &str) -> Option<usize> {
+        if let Some(rest) = line.strip_prefix(BREAKING_CHANGE) {
+            let spaces = rest.len() - rest.trim_start().len();
+            if rest
+                .trim_start()
+                .starts_with(|c| self.separators.contains(c))
+            {
+                return Some(BREAKING_CHANGE.len() + spaces);
+            }
+        }
+        let mut spaces = false;
+        for (index, c) in line.char_indices() {
+            match c {
+                c if index > 0 && self.separators.contains(c) => return Some(index),
+                c if !spaces && (c.is_ascii_alphanumeric() || c == '-') => {}
+                ' ' | '\t' if index > 0 => spaces = true,
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// Whether the line starts with a key git generates or a configured one.
+    fn is_recognized(&self, line: &str) -> bool {
+        if GIT_GENERATED_PREFIXES.iter().any(|p| line.starts_with(p)) {
+            return true;
+        }
+        self.separator(line).is_some_and(|index| {
+            let key = line[..index].trim_end();
+            self.token(key).is_some()
+        })
+    }
+}
+
+fn parse_where(key: &str, value: &str) -> Result<Where, anyhow::Error> {
+    match value.to_ascii_lowercase().as_str() {
+        "end" => Ok(Where::End),
+        "start" => Ok(Where::Start),
+        "after" => Ok(Where::After),
+        "before" => Ok(Where::Before),
+        _ => Err(anyhow!(
+            "Invalid git config {}: expected end, start, after or before",
+            key
+        )),
+    }
+}
+
+fn parse_if_exists(key: &str, value: &str) -> Result<IfExists, anyhow::Error> {
+    match value.to_ascii_lowercase().as_str() {
+        "addifdifferentneighbor" => Ok(IfExists::AddIfDifferentNeighbor),
+        "addifdifferent" => Ok(IfExists::AddIfDifferent),
+        "add" => Ok(IfExists::Add),
+        "replace" => Ok(IfExists::Replace),
+        "donothing" => Ok(IfExists::DoNothing),
+        _ => Err(anyhow!(
+            "Invalid git config {}: expected addIfDifferentNeighbor, addIfDifferent, add, replace or doNothing",
+            key
+        )),
+    }
+}
+
+fn parse_if_missing(key: &str, value: &str) -> Result<IfMissing, anyhow::Error> {
+    match value.to_ascii_lowercase().as_str() {
+        "add" => Ok(IfMissing::Add),
+        "donothing" => Ok(IfMissing::DoNothing),
+        _ => Err(anyhow!(
+            "Invalid git config {}: expected add or doNothing",
+            key
+        )),
+    }
+}
+
+/// A line of a trailer block, which may also have lines which are not trailers.
+#[derive(Debug)]
+enum Line {
+    Trailer(String, String),
+    Other(String),
+}
+
+/// Adds the trailers to a message the way `git interpret-trailers --trailer` does: to the trailer
+/// block the message may already end with, following the `trailer.*` git configuration.
+pub fn interpret(message: &str, trailers: &[(String, String)], config: &Config) -> String {
+    let lines: Vec<&str> = message.trim_end().lines().map(str::trim_end).collect();
+    let start = block_start(&lines, config);
+    let mut block = parse_block(&lines[start.unwrap_or(lines.len())..], config);
+    for (key, value) in trailers {
+        let key = config.key(key);
+        apply(&mut block, &key, value.trim(), config);
+    }
+    if block.is_empty() {
+        return message.to_string();
+    }
+
+    let text = lines[..start.unwrap_or(lines.len())].join("\n");
+    let block: Vec<String> = block
+        .iter()
+        .map(|line| match line {
+            Line::Trailer(key, value) => config.format(key, value),
+            Line::Other(line) => line.clone(),
+        })
+        .collect();
+    format!("{}\n\n{}", text.trim_end(), block.join("\n"))
+}
+
+/// Splits a message into the text before its trailer block and its trailers, the way
+/// `git interpret-trailers` finds them. The keys are without their separator, the values on a
+/// single line, and the other lines of a block which also has some are left in the text.
+pub fn split_block(message: &str, config: &Config) -> (String, Vec<(String, String)>) {
+    let lines: Vec<&str> = message.trim_end().lines().map(str::trim_end).collect();
+    let Some(start) = block_start(&lines, config) else {
+        return (message.to_string(), vec![]);
+    };
+    let mut text = lines[..start].join("\n").trim_end().to_string();
+    let (mut trailers, mut others) = (vec![], vec![]);
+    for line in parse_block(&lines[start..], config) {
+        match line {
+            Line::Trailer(key, value) => {
+                let value: Vec<&str> = value.lines().map(str::trim).collect();
+                trailers.push((config.without_separator(&key).to_string(), value.join(" ")));
+            }
+            Line::Other(line) => others.push(line),
+        }
+    }
+    if !others.is_empty() {
+        text = format!("{}\n\n{}", text, others.join("\n"));
+    }
+    (text, trailers)
+}
+
+fn parse_block(lines: &[&str], config: &Config) -> Vec<Line> {
+    let mut block: Vec<Line> = vec![];
+    for line in lines {
+        match (line.starts_with(char::is_whitespace), block.last_mut()) {
+            (true, Some(Line::Trailer(_, value))) => {
+                value.push('\n');
+                value.push_str(line);
+            }
+            _ => match config.separator(line) {
+                Some(index) => {
+                    let key = config.key(line[..index].trim_end());
+                    block.push(Line::Trailer(key, line[index + 1..].trim().to_string()));
+                }
+                None => block.push(Line::Other(line.to_string())),
+            },
+        }
+    }
+    block
+}
+
+/// The index of the first line of the last paragraph when it is a trailer block, i.e. when all its
+/// lines are trailers, or a quarter of them with at least one git generated or configured trailer.
+fn block_start(lines: &[&str], config: &Config) -> Option<usize> {
+    // the first paragraph is the subject, not trailers
+    lines.iter().position(|l| l.is_empty())?;
+    let start = lines.iter().rposition(|l| l.is_empty())? + 1;
+    let (mut trailers, mut others, mut recognized) = (0, 0, false);
+    let mut last_is_trailer = false;
+    for line in &lines[start..] {
+        if line.starts_with(char::is_whitespace) {
+            match last_is_trailer {
+                true => trailers += 1,
+                false => others += 1,
+            }
+            continue;
+        }
+        last_is_trailer = config.is_recognized(line) || config.separator(line).is_some();
+        recognized |= config.is_recognized(line);
+        match last_is_trailer {
+            true => trailers += 1,
+            false => others += 1,
+        }
+    }
+    let is_block = (trailers > 0 && others == 0) || (recognized && trailers * 3 >= others);
+    is_block.then_some(start)
+}
+
+/// Adds a trailer to the block, following its `where`, `ifExists` and `ifMissing` configuration.
+fn apply(block: &mut Vec<Line>, key: &str, value: &str, config: &Config) {
+    let token = config.token(key);
+    let place = token.and_then(|t| t.place).unwrap_or(config.place);
+    let backwards = matches!(place, Where::End | Where::After);
+    let middle = matches!(place, Where::After | Where::Before);
+    let same_key = |line: &Line| match line {
+        Line::Trailer(k, _) => config
+            .without_separator(k)
+            .eq_ignore_ascii_case(config.without_separator(key)),
+        Line::Other(_) => false,
+    };
+    let same = |line: &Line| match line {
+        Line::Trailer(_, v) => same_key(line) && v.eq_ignore_ascii_case(value),
+        Line::Other(_) => false,
+    };
+    let trailer = || Line::Trailer(key.to_string(), value.to_string());
+
+    let found = match backwards {
+        true => block.iter().rposition(same_key),
+        false => block.iter().position(same_key),
+    };
+    let Some(found) = found else {
+        match token
+            .and_then(|t| t.if_missing)
+            .unwrap_or(config.if_missing)
+        {
+            IfMissing::Add if backwards => block.push(trailer()),
+            IfMissing::Add => block.insert(0, trailer()),
+            IfMissing::DoNothing => {}
+        }
+        return;
+    };
+
+    // the trailer is added next to the one found, or at the end or the start of the block
+    let neighbor = match (middle, backwards) {
+        (true, _) => found,
+        (false, true) => block.len() - 1,
+        (false, false) => 0,
+    };
+    let index = if backwards { neighbor + 1 } else { neighbor };
+    match token.and_then(|t| t.if_exists).unwrap_or(config.if_exists) {
+        IfExists::DoNothing => {}
+        IfExists::Add => block.insert(index, trailer()),
+        IfExists::Replace => {
+            block.insert(index, trailer());
+            block.remove(if found >= index { found + 1 } else { found });
+        }
+        IfExists::AddIfDifferent => {
+            if !block.iter().any(same) {
+                block.insert(index, trailer());
+            }
+        }
+        IfExists::AddIfDifferentNeighbor => {
+            if !same(&block[neighbor]) {
+                block.insert(index, trailer());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::trailers::{interpret, parse, split, split_block, Config};
+
+    #[test]
+    fn test_parse_trailers() {
+        let message = "First commit\n\nSome details: here\n\nIssue: #42\nCo-authored-by: Jane Doe <jane.doe@example.org>\n";
+
+        assert_eq!(
+            parse(message, &Config::default()),
+            vec![
+                ("Issue".to_string(), "#42".to_string()),
+                (
+                    "Co-authored-by".to_string(),
+                    "Jane Doe <jane.doe@example.org>".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_trailers_ignoring_comments_and_continuation_lines() {
+        let message = "First commit\n\nNote: a long\n  value\n# Please enter the commit message\n";
+
+        assert_eq!(
+            parse(message, &Config::default()),
+            vec![("Note".to_string(), "a long value".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_no_trailers() {
+        assert!(parse("Fix: first commit\n", &Config::default()).is_empty());
+        assert!(parse(
+            "First commit\n\nThis is not a trailer\nIssue: #42\n",
+            &Config::default()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_parse_breaking_change() {
+        assert_eq!(
+            parse(
+                "feat!: drop v1\n\nBREAKING CHANGE: v1 is gone\nIssue: #42\n",
+                &Config::default()
+            ),
+            vec![
+                ("BREAKING CHANGE".to_string(), "v1 is gone".to_string()),
+                ("Issue".to_string(), "#42".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_message_and_trailers() {
+        let message = "First commit\n\nSome details\n\nIssue: #42\n";
+
+        assert_eq!(
+            split(message, &Config::default()),
+            (
+                "First commit\n\nSome details".to_string(),
+                vec![("Issue".to_string(), "#42".to_string())]
+            )
+        );
+        assert_eq!(
+            split("First commit\n\nSome details\n", &Config::default()),
+            ("First commit\n\nSome details".to_string(), vec![])
+        );
+    }
+
+    fn trailers(trailers: &[(&str, &str)]) -> Vec<(String, String)> {
+        trailers
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn config(entries: &[(&str, &str)]) -> Config {
+        Config::from_entries(&trailers(entries)).unwrap()
+    }
+
+    #[test]
+    fn test_interpret() {
+        let config = Config::default();
+
+        assert_eq!(
+            interpret("First commit", &trailers(&[("Issue", "#42")]), &config),
+            "First commit\n\nIssue: #42"
+        );
+        assert_eq!(interpret("First commit\n", &[], &config), "First commit\n");
+        assert_eq!(
+            interpret(
+                "First commit\n\nSee: the docs\nIssue: #42\n",
+                &trailers(&[("Issue", "#42"), ("Issue", "#43"), ("Issue", "#42")]),
+                &config
+            ),
+            "First commit\n\nSee: the docs\nIssue: #42\nIssue: #43\nIssue: #42"
+        );
+        assert_eq!(
+            interpret(
+                "First commit\n\nThis is not a trailer\nIssue: #42",
+                &trailers(&[("Issue", "#43")]),
+                &config
+            ),
+            "First commit\n\nThis is not a trailer\nIssue: #42\n\nIssue: #43"
+        );
+        assert_eq!(
+            interpret(
+                "First commit\n\nThis is not a trailer\nSigned-off-by: Jane Doe <jane.doe@example.org>",
+                &trailers(&[("Issue", "#43")]),
+                &config
+            ),
+            "First commit\n\nThis is not a trailer\nSigned-off-by: Jane Doe <jane.doe@example.org>\nIssue: #43"
+        );
+    }
+
+    #[test]
+    fn test_interpret_with_git_config() {
+        let config = config(&[
+            ("trailer.separators", ":#"),
+            ("trailer.bug.key", "Bug #"),
+            ("trailer.bug.where", "start"),
+            ("trailer.issue.ifexists", "replace"),
+            ("trailer.see.ifmissing", "doNothing"),
+            ("trailer.Co-authored-by.where", "after"),
+            ("trailer.Co-authored-by.ifExists", "addIfDifferent"),
+        ]);
+        let message = "First commit\n\nIssue: #1\nCo-authored-by: Jane Doe <jane.doe@example.org>\nSigned-off-by: Joe Shmoe <joe.shmoe@example.org>";
+
+        assert_eq!(
+            interpret(
+                message,
+                &trailers(&[
+                    ("bug", "42"),
+                    ("Issue", "#2"),
+                    ("See", "the docs"),
+                    ("Co-authored-by", "James Smith <james.smith@example.org>"),
+                    ("Co-authored-by", "jane doe <jane.doe@example.org>"),
+                ]),
+                &config
+            ),
+            "First commit\n\nBug #42\nCo-authored-by: Jane Doe <jane.doe@example.org>\nCo-authored-by: James Smith <james.smith@example.org>\nSigned-off-by: Joe Shmoe <joe.shmoe@example.org>\nIssue: #2"
+        );
+    }
+
+    #[test]
+    fn test_split_block() {
+        let config = config(&[("trailer.separators", ":#"), ("trailer.bug.key", "Bug #")]);
+
+        assert_eq!(
+            split_block(
+                "First commit\n\nSome details\n\nBug #42\nIssue: #1\n",
+                &config
+            ),
+            (
+                "First commit\n\nSome details".to_string(),
+                trailers(&[("Bug", "42"), ("Issue", "#1")])
+            )
+        );
+        assert_eq!(
+            split_block(
+                "First commit\n\nSome details\nSigned-off-by: Jane Doe <jane.doe@example.org>\nNote: a long\n  value\n",
+                &config
+            ),
+            (
+                "First commit\n\nSome details".to_string(),
+                trailers(&[
+                    ("Signed-off-by", "Jane Doe <jane.doe@example.org>"),
+                    ("Note", "a long value")
+                ])
+            )
+        );
+        assert_eq!(
+            split_block("First commit\n", &config),
+            ("First commit\n".to_string(), vec![])
+        );
+    }
+
+    #[test]
+    fn test_parse_trailers_with_git_config() {
+        let config = config(&[
+            ("trailer.separators", ":#"),
+            ("trailer.issue.key", "Issue #"),
+        ]);
+        let message = interpret("Fix the build", &trailers(&[("Issue", "42")]), &config);
+
+        assert_eq!(message, "Fix the build\n\nIssue #42");
+        assert_eq!(parse(&message, &config), trailers(&[("Issue", "42")]));
+    }
+
+    #[test]
+    fn test_parse_trailers_of_a_block_with_other_lines() {
+        let config = Config::default();
+        let message = interpret(
+            "Fix the build\n\nSome text\nSigned-off-by: Jane Doe <jane.doe@example.org>",
+            &trailers(&[("Issue", "1")]),
+            &config,
+        );
+
+        assert_eq!(
+            parse(&message, &config),
+            trailers(&[
+                ("Signed-off-by", "Jane Doe <jane.doe@example.org>"),
+                ("Issue", "1")
+            ])
+        );
+        assert_eq!(
+            split(&message, &config).0,
+            "Fix the build\n\nSome text".to_string()
+        );
+    }
+}
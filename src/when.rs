@@ -0,0 +1,178 @@
+use crate::git::Git;
+use crate::structure::When;
+use anyhow::anyhow;
+use regex::Regex;
+
+/// What the conditions of the trailers are evaluated against, the branch and the staged files
+/// being read once, when a condition needs them.
+pub struct Context<'a, G: Git> {
+    git: Option<&'a G>,
+    branch: Option<Option<String>>,
+    staged_files: Option<Vec<String>>,
+}
+
+impl<'a, G: Git> Context<'a, G> {
+    pub fn new(git: &'a G) -> Context<'a, G> {
+        Context {
+            git: Some(git),
+            branch: None,
+            staged_files: None,
+        }
+    }
+
+    /// A context for a past commit, where only the conditions on answers can be evaluated.
+    pub fn answers_only() -> Context<'a, G> {
+        Context {
+            git: None,
+            branch: None,
+            staged_files: None,
+        }
+    }
+
+    fn git(&self) -> Result<&'a G, anyhow::Error> {
+        self.git
+            .ok_or_else(|| anyhow!("the branch and the staged files of a past commit are unknown"))
+    }
+
+    /// Whether all the conditions hold, given the answers to the previous trailers.
+    pub fn holds(
+        &mut self,
+        when: &When,
+        answers: &[(String, String)],
+    ) -> Result<bool, anyhow::Error> {
+        if !when.branch.is_empty() {
+            if self.branch.is_none() {
+                self.branch = Some(self.git()?.current_branch()?);
+            }
+            let branch = self.branch.as_ref().and_then(|b| b.as_deref());
+            match branch {
+                Some(branch) if when.branch.iter().any(|p| glob(p, branch)) => {}
+                _ => return Ok(false),
+            }
+        }
+
+        if !when.paths.is_empty() {
+            if self.staged_files.is_none() {
+                self.staged_files = Some(self.git()?.staged_files()?);
+            }
+            let staged_files = self.staged_files.as_deref().unwrap_or_default();
+            let staged = staged_files
+                .iter()
+                .any(|file| when.paths.iter().any(|p| glob(p, file)));
+            if !staged {
+                return Ok(false);
+            }
+        }
+
+        for (name, pattern) in &when.answers {
+            let answered = answers
+                .iter()
+                .any(|(key, value)| key.eq_ignore_ascii_case(name) && glob(pattern, value));
+            if !answered {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Whether the text matches the glob pattern, where `*` matches anything but `/`, `**` matches
+/// anything and `?` matches one character but `/`.
+pub fn glob(pattern: &str, text: &str) -> bool {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no directory at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).is_ok_and(|regex| regex.is_match(text))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::git::MockGit;
+    use crate::structure::When;
+    use crate::when::{glob, Context};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_glob() {
+        assert!(glob("feature/*", "feature/login"));
+        assert!(!glob("feature/*", "feature/login/form"));
+        assert!(!glob("feature/*", "bugfix/login"));
+        assert!(glob("src/**", "src/ui/form.rs"));
+        assert!(glob("**/*.rs", "main.rs"));
+        assert!(glob("docs/**/*.md", "docs/config/schema.md"));
+        assert!(glob("v?.x", "v1.x"));
+        assert!(glob("yes", "yes"));
+        assert!(!glob("y", "yes"));
+    }
+
+    #[test]
+    fn test_holds() {
+        let mut git: MockGit = MockGit::new();
+        git.expect_current_branch()
+            .times(1)
+            .returning(|| Ok(Some("feature/login".to_string())));
+        git.expect_staged_files()
+            .times(1)
+            .returning(|| Ok(vec!["README.md".to_string(), "src/main.rs".to_string()]));
+        let mut context = Context::new(&git);
+        let answers = vec![("Breaking-change".to_string(), "yes".to_string())];
+
+        let when = |branch: &str, paths: &str, answer: &str| When {
+            branch: vec![branch.to_string()],
+            paths: vec![paths.to_string()],
+            answers: BTreeMap::from([("breaking-change".to_string(), answer.to_string())]),
+        };
+
+        assert!(context
+            .holds(&when("feature/*", "src/**", "yes"), &answers)
+            .unwrap());
+        assert!(!context
+            .holds(&when("main", "src/**", "yes"), &answers)
+            .unwrap());
+        assert!(!context
+            .holds(&when("feature/*", "docs/**", "yes"), &answers)
+            .unwrap());
+        assert!(!context
+            .holds(&when("feature/*", "src/**", "no"), &answers)
+            .unwrap());
+        assert!(context.holds(&When::default(), &[]).unwrap());
+    }
+
+    #[test]
+    fn test_holds_answers_only() {
+        let mut context: Context<MockGit> = Context::answers_only();
+        let answers = vec![("Breaking-change".to_string(), "yes".to_string())];
+        let when = When {
+            answers: BTreeMap::from([("Breaking-change".to_string(), "yes".to_string())]),
+            ..Default::default()
+        };
+
+        assert!(context.holds(&when, &answers).unwrap());
+        assert!(context
+            .holds(
+                &When {
+                    branch: vec!["main".to_string()],
+                    ..Default::default()
+                },
+                &answers
+            )
+            .is_err());
+    }
+}